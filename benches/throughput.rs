@@ -0,0 +1,111 @@
+// Boards generated and games played per second on the flat board, run with
+// `cargo bench`. Every run uses the same seeds, so numbers compare across changes
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use ::minesweeper::minesweeper::{self, ClueRule, MinesweeperBoard};
+use ::minesweeper::placement::Placement;
+use ::minesweeper::solver;
+use ::minesweeper::topology::{SquareGrid, Topology};
+use ::minesweeper::visible::VisibleBoard;
+
+// How long each measurement keeps repeating its work
+const RUN_TIME: Duration = Duration::from_secs(2);
+
+fn main() {
+    let expert = SquareGrid::new(30, 16);
+    let huge = SquareGrid::new(1000, 1000);
+
+    let mut rng = StdRng::seed_from_u64(1);
+    measure("generate 30x16, 99 mines", "boards", || {
+        build(&expert, 99, &mut rng);
+    });
+    let mut rng = StdRng::seed_from_u64(2);
+    measure("generate 1000x1000, 200k mines", "boards", || {
+        build(&huge, 200_000, &mut rng);
+    });
+    let mut rng = StdRng::seed_from_u64(3);
+    measure("random clicks 30x16, 99 mines", "games", || {
+        let mut board = build(&expert, 99, &mut rng);
+        play_randomly(&mut board, &expert, &mut rng);
+    });
+    let mut rng = StdRng::seed_from_u64(4);
+    measure("solver 30x16, 99 mines", "games", || {
+        let mut board = build(&expert, 99, &mut rng);
+        play_with_solver(&mut board, &expert, 99, &mut rng);
+    });
+}
+
+// Repeats work for RUN_TIME and prints how often it ran per second
+fn measure(name: &str, unit: &str, mut work: impl FnMut()) {
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < RUN_TIME {
+        work();
+        runs += 1;
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    println!(
+        "{:<34} {:>12.1} {}/s ({} in {:.2}s)",
+        name,
+        runs as f64 / seconds,
+        unit,
+        runs,
+        seconds
+    );
+}
+
+fn build(topology: &dyn Topology, mine_count: i32, rng: &mut StdRng) -> MinesweeperBoard {
+    minesweeper::build_minesweeper_board(
+        topology,
+        mine_count,
+        1,
+        ClueRule::Standard,
+        &Placement::default(),
+        rng,
+    )
+    .expect("Could not build board")
+}
+
+// Clicks hidden tiles at random until a mine goes off or the board is clear
+fn play_randomly(board: &mut MinesweeperBoard, topology: &dyn Topology, rng: &mut StdRng) {
+    let [width, height] = topology.dimensions();
+    let safe_tiles = width * height - board.tiles().filter(|t| t.has_mine()).count() as i32;
+    while minesweeper::revealed_safe_tiles(board) < safe_tiles {
+        let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+        if minesweeper::reveal_tile(board, topology, x, y).is_err() {
+            return;
+        }
+    }
+}
+
+// Reveals whatever the solver proves safe, guessing at random when it proves nothing
+fn play_with_solver(
+    board: &mut MinesweeperBoard,
+    topology: &dyn Topology,
+    mine_count: i32,
+    rng: &mut StdRng,
+) {
+    let [width, height] = topology.dimensions();
+    let safe_tiles = width * height - mine_count;
+    while minesweeper::revealed_safe_tiles(board) < safe_tiles {
+        let view = VisibleBoard::new(board);
+        let deductions = solver::deduce(&view, topology, ClueRule::Standard, 1, mine_count);
+        let mut clicks = deductions.safe;
+        if clicks.is_empty() {
+            let hidden: Vec<[i32; 2]> = topology
+                .positions()
+                .into_iter()
+                .filter(|[x, y]| !board.is_revealed(*x, *y))
+                .collect();
+            clicks.push(hidden[rng.gen_range(0..hidden.len())]);
+        }
+        for [x, y] in clicks {
+            if minesweeper::reveal_tile(board, topology, x, y).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+// Writes the C header for src/ffi.rs when built with the c-header feature. The header
+// is checked in, so building the library alone doesn't need cbindgen
+fn main() {
+    #[cfg(feature = "c-header")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/lib.rs");
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        cbindgen::generate(&crate_dir)
+            .expect("Couldn't generate the C header")
+            .write_to_file(format!("{}/include/minesweeper.h", crate_dir));
+    }
+}
@@ -0,0 +1,98 @@
+/*
+ * Exercises the C API against the release library:
+ *
+ *   cargo build --release
+ *   cc c/test_minesweeper.c -Iinclude -Ltarget/release -lminesweeper -o target/test_minesweeper
+ *   LD_LIBRARY_PATH=target/release target/test_minesweeper
+ */
+#include <stdio.h>
+
+#include "minesweeper.h"
+
+static int failures = 0;
+
+#define CHECK(condition)                                              \
+    do {                                                              \
+        if (!(condition)) {                                           \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition); \
+            failures++;                                               \
+        }                                                             \
+    } while (0)
+
+static void test_bad_arguments(void) {
+    CHECK(ms_game_new(3, 3, 10, 1) == NULL);
+    CHECK(ms_game_new(0, 3, 1, 1) == NULL);
+    CHECK(ms_game_reveal(NULL, 0, 0) == MS_ERROR_NULL);
+    CHECK(ms_game_status(NULL) == MS_ERROR_NULL);
+    ms_game_free(NULL);
+
+    MsGame *game = ms_game_new(9, 9, 10, 1);
+    CHECK(game != NULL);
+    CHECK(ms_game_reveal(game, 9, 0) == MS_ERROR_OFF_BOARD);
+    CHECK(ms_game_tile(game, -1, 0) == MS_ERROR_OFF_BOARD);
+    ms_game_free(game);
+}
+
+static void test_no_mines_wins_at_once(void) {
+    MsGame *game = ms_game_new(4, 3, 0, 7);
+    CHECK(ms_game_width(game) == 4);
+    CHECK(ms_game_height(game) == 3);
+    CHECK(ms_game_tile(game, 2, 1) == MS_TILE_HIDDEN);
+    CHECK(ms_game_reveal(game, 0, 0) == 12);
+    CHECK(ms_game_tile(game, 2, 1) == 0);
+    CHECK(ms_game_status(game) == MS_STATUS_WON);
+    ms_game_free(game);
+}
+
+static void test_flags(void) {
+    MsGame *game = ms_game_new(9, 9, 10, 2);
+    CHECK(ms_game_flag(game, 4, 4) == 1);
+    CHECK(ms_game_tile(game, 4, 4) == MS_TILE_FLAGGED);
+    CHECK(ms_game_mines_left(game) == 9);
+    CHECK(ms_game_reveal(game, 4, 4) == 0);
+    CHECK(ms_game_flag(game, 4, 4) == 0);
+    CHECK(ms_game_tile(game, 4, 4) == MS_TILE_HIDDEN);
+    CHECK(ms_game_mines_left(game) == 10);
+    ms_game_free(game);
+}
+
+/* Revealing every tile in turn hits a mine unless the board is cleared first */
+static void test_play_to_the_end(void) {
+    MsGame *game = ms_game_new(9, 9, 10, 3);
+    for (int y = 0; y < 9 && ms_game_status(game) == MS_STATUS_PLAYING; y++) {
+        for (int x = 0; x < 9 && ms_game_status(game) == MS_STATUS_PLAYING; x++) {
+            int tile = ms_game_tile(game, x, y);
+            CHECK(tile >= 0 && tile <= MS_TILE_QUESTIONED);
+            if (tile == MS_TILE_HIDDEN) {
+                CHECK(ms_game_reveal(game, x, y) >= 0);
+            } else {
+                CHECK(ms_game_chord(game, x, y) >= 0);
+            }
+        }
+    }
+    CHECK(ms_game_status(game) == MS_STATUS_LOST);
+    CHECK(ms_game_reveal(game, 8, 8) == 0);
+
+    int mines = 0, exploded = 0;
+    for (int y = 0; y < 9; y++) {
+        for (int x = 0; x < 9; x++) {
+            int tile = ms_game_tile(game, x, y);
+            mines += tile == MS_TILE_MINE;
+            exploded += tile == MS_TILE_EXPLODED;
+        }
+    }
+    CHECK(mines == 9);
+    CHECK(exploded == 1);
+    ms_game_free(game);
+}
+
+int main(void) {
+    test_bad_arguments();
+    test_no_mines_wins_at_once();
+    test_flags();
+    test_play_to_the_end();
+    if (failures == 0) {
+        printf("All C API checks passed\n");
+    }
+    return failures != 0;
+}
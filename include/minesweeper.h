@@ -0,0 +1,144 @@
+/*
+ * Minesweeper engine. Start a game with ms_game_new, make moves on it and free it
+ * with ms_game_free. Coordinates are (x, y) from the top left tile.
+ *
+ * Calls on a game return MS_ERROR_NULL for a NULL game, MS_ERROR_OFF_BOARD for a
+ * tile off the board and MS_ERROR_PANIC if the engine failed. Moves after the game
+ * is over open nothing.
+ */
+
+#ifndef MINESWEEPER_H
+#define MINESWEEPER_H
+
+/* Generated by cbindgen from src/ffi.rs. Rebuild with --features c-header rather than editing */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define MS_ERROR_NULL -1
+
+#define MS_ERROR_OFF_BOARD -2
+
+#define MS_ERROR_PANIC -3
+
+#define MS_STATUS_PLAYING 0
+
+#define MS_STATUS_LOST 1
+
+#define MS_STATUS_WON 2
+
+#define MS_TILE_HIDDEN 9
+
+#define MS_TILE_FLAGGED 10
+
+#define MS_TILE_QUESTIONED 11
+
+#define MS_TILE_MINE 12
+
+#define MS_TILE_EXPLODED 13
+
+#define MS_TILE_WRONG_FLAG 14
+
+typedef struct MsGame MsGame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Starts a game. The same seed always gives the same board. Returns NULL if the
+ mines don't fit on the board
+ */
+struct MsGame *ms_game_new(int32_t width, int32_t height, int32_t mines, uint64_t seed);
+
+/*
+ Frees a game from ms_game_new. NULL is ignored.
+
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new, and is not used again
+ */
+void ms_game_free(struct MsGame *game);
+
+/*
+ Reveals the tile, returning how many tiles opened. Revealing a mine loses the game
+
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new, not used by another thread
+ during the call
+ */
+int32_t ms_game_reveal(struct MsGame *game, int32_t x, int32_t y);
+
+/*
+ Reveals the neighbours of a revealed number with as many flags around it,
+ returning how many tiles opened
+
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new, not used by another thread
+ during the call
+ */
+int32_t ms_game_chord(struct MsGame *game, int32_t x, int32_t y);
+
+/*
+ Toggles a flag on a hidden tile, returning 1 if it is now flagged and 0 if not
+
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new, not used by another thread
+ during the call
+ */
+int32_t ms_game_flag(struct MsGame *game, int32_t x, int32_t y);
+
+/*
+ What the player sees on the tile: its number 0 to 8 once revealed, otherwise
+ MS_TILE_HIDDEN, MS_TILE_FLAGGED or MS_TILE_QUESTIONED. Once the game is over,
+ unflagged mines show as MS_TILE_MINE, the one that was hit as MS_TILE_EXPLODED and
+ flags on safe tiles as MS_TILE_WRONG_FLAG
+
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new
+ */
+int32_t ms_game_tile(const struct MsGame *game, int32_t x, int32_t y);
+
+/*
+ MS_STATUS_PLAYING, MS_STATUS_LOST or MS_STATUS_WON
+
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new
+ */
+int32_t ms_game_status(const struct MsGame *game);
+
+/*
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new
+ */
+int32_t ms_game_width(const struct MsGame *game);
+
+/*
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new
+ */
+int32_t ms_game_height(const struct MsGame *game);
+
+/*
+ Mines less flags placed, as the counter shows
+
+ # Safety
+
+ game must be NULL or a live pointer from ms_game_new
+ */
+int32_t ms_game_mines_left(const struct MsGame *game);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MINESWEEPER_H */
@@ -0,0 +1,106 @@
+import pytest
+
+import minesweeper
+
+
+def test_same_seed_same_board():
+    a = minesweeper.Game(16, 16, 40, seed=3)
+    b = minesweeper.Game(16, 16, 40, seed=3)
+    assert a.three_bv() == b.three_bv()
+    assert [a.reveal(x, 0) for x in range(16)] == [b.reveal(x, 0) for x in range(16)]
+    assert a.status == b.status
+
+
+def test_too_many_mines():
+    with pytest.raises(ValueError):
+        minesweeper.Game(3, 3, 10)
+
+
+def test_off_board():
+    game = minesweeper.Game(5, 5, 3, seed=1)
+    with pytest.raises(IndexError):
+        game.reveal(5, 0)
+
+
+def test_view_shape_and_values():
+    game = minesweeper.Game(9, 6, 10, seed=7)
+    view = game.view()
+    assert view.shape == (6, 9)
+    assert view.format == "b"
+    assert all(view[y, x] == -1 for y in range(6) for x in range(9))
+
+    assert game.flag(0, 0) == 1
+    assert game.view()[0, 0] == -2
+    assert game.mines_left == 9
+
+
+def test_mines_shown_after_loss():
+    game = minesweeper.Game(9, 9, 10, seed=7)
+    assert all(-4 not in row for row in game.view().tolist())
+    for y in range(9):
+        for x in range(9):
+            if game.status == "playing" and game.view()[y, x] == -1:
+                game.reveal(x, y)
+    assert game.status == "lost"
+    values = [v for row in game.view().tolist() for v in row]
+    assert values.count(-5) == 1
+    assert values.count(-4) == 9
+
+
+def test_wrong_flags_shown_after_loss():
+    game = minesweeper.Game(9, 9, 10, seed=7)
+    for x in range(9):
+        game.flag(x, 8)
+    for y in range(8):
+        for x in range(9):
+            if game.status == "playing" and game.view()[y, x] == -1:
+                game.reveal(x, y)
+    assert game.status == "lost"
+    values = [v for row in game.view().tolist() for v in row]
+    last_row = game.view().tolist()[8]
+    assert all(v in (-2, -6) for v in last_row)
+    assert values.count(-2) + values.count(-4) == 9
+    assert values.count(-6) == 9 - last_row.count(-2)
+
+
+def test_solver_plays_to_the_end():
+    game = minesweeper.Game(9, 9, 10, seed=7)
+    while game.status == "playing":
+        safe, mines = game.deduce()
+        for x, y in mines:
+            if game.view()[y, x] != -2:
+                game.flag(x, y)
+        if safe:
+            for x, y in safe:
+                game.reveal(x, y)
+        else:
+            chances = game.probabilities()
+            x, y = min(
+                ((x, y) for y in range(9) for x in range(9) if chances[y, x] >= 0.0
+                 and game.view()[y, x] == -1),
+                key=lambda p: chances[p[1], p[0]],
+            )
+            game.reveal(x, y)
+    assert game.status in ("won", "lost")
+    if game.status == "won":
+        assert game.revealed_safe_tiles() == 81 - 10
+
+
+def test_probabilities_sum_to_mine_count():
+    game = minesweeper.Game(16, 16, 40, seed=11)
+    game.reveal(8, 8)
+    if game.status == "playing":
+        chances = game.probabilities()
+        total = sum(chances[y, x] for y in range(16) for x in range(16) if chances[y, x] >= 0.0)
+        assert total == pytest.approx(40.0)
+
+
+def test_numpy_views():
+    np = pytest.importorskip("numpy")
+    game = minesweeper.Game(9, 6, 10, seed=7)
+    view = np.asarray(game.view())
+    assert view.shape == (6, 9)
+    assert view.dtype == np.int8
+    chances = np.asarray(game.probabilities())
+    assert chances.dtype == np.float64
+    assert chances.sum() == pytest.approx(10.0)
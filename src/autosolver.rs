@@ -0,0 +1,99 @@
+use rand::rngs::StdRng;
+
+use crate::minesweeper::ClueRule;
+use crate::simulation;
+use crate::simulation::{Action, Move, Strategy};
+use crate::solver;
+use crate::topology::Topology;
+use crate::visible::VisibleBoard;
+
+// The built-in player. It makes every move the numbers prove first, flagging proven
+// mines as it goes, and only when nothing is proven reveals the hidden tile least
+// likely to hold a mine. Its results are the baseline to check solver changes against
+pub struct Autosolver {
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+}
+
+impl Default for Autosolver {
+    fn default() -> Self {
+        Autosolver::new(ClueRule::Standard, 1)
+    }
+}
+
+impl Autosolver {
+    pub fn new(clue_rule: ClueRule, max_per_tile: i32) -> Self {
+        Autosolver {
+            clue_rule,
+            max_per_tile,
+        }
+    }
+
+    // The moves to make next: flags on proven mines and reveals of proven safe tiles,
+    // or a single guess when there are none. mine_count is every mine on the board,
+    // flagged or not
+    pub fn next_moves(
+        &self,
+        board: &VisibleBoard,
+        topology: &dyn Topology,
+        mine_count: i32,
+    ) -> Vec<Move> {
+        let deductions = solver::deduce(
+            board,
+            topology,
+            self.clue_rule,
+            self.max_per_tile,
+            mine_count,
+        );
+        let mut moves = simulation::proven_moves(board, &deductions);
+        if moves.iter().any(|m| m.action == Action::Reveal) {
+            return moves;
+        }
+
+        // Of the likeliest safe tiles, those with fewest neighbours are the likeliest
+        // to be a 0 and open an area
+        let chances = solver::probabilities(
+            board,
+            topology,
+            self.clue_rule,
+            self.max_per_tile,
+            mine_count,
+        );
+        let best = chances
+            .iter()
+            .filter(|([x, y], _)| !board.is_flagged(*x, *y))
+            .min_by(|(a, chance_a), (b, chance_b)| {
+                chance_a.total_cmp(chance_b).then(
+                    topology
+                        .neighbours(a[0], a[1])
+                        .len()
+                        .cmp(&topology.neighbours(b[0], b[1]).len()),
+                )
+            });
+        if let Some(&([x, y], _)) = best {
+            moves.push(Move {
+                action: Action::Reveal,
+                x,
+                y,
+                guess: true,
+            });
+        }
+        moves
+    }
+}
+
+impl Strategy for Autosolver {
+    fn name(&self) -> &str {
+        "autosolver"
+    }
+
+    fn moves(
+        &self,
+        board: &VisibleBoard,
+        topology: &dyn Topology,
+        mine_count: i32,
+        _rng: &mut StdRng,
+    ) -> Vec<Move> {
+        self.next_moves(board, topology, mine_count)
+    }
+}
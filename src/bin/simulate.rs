@@ -0,0 +1,125 @@
+use std::fs;
+use std::thread;
+
+use ::minesweeper::autosolver::Autosolver;
+use ::minesweeper::options::{option_value, option_values};
+use ::minesweeper::simulation;
+use ::minesweeper::simulation::{Configuration, RandomClicker, SolverPlayer, Strategy};
+
+const GAMES: usize = 1000;
+const SEED: u64 = 1;
+
+// Usage:
+//   simulate [--games <n>] [--seed <n>] [--threads <n>] [--config <WxHxM>]...
+//            [--strategy <random|solver|autosolver>]... [--csv <file>] [--json <file>]
+//
+// Plays every strategy on every configuration, n games each, the same seeds giving the
+// same boards to every strategy. Configurations default to beginner, intermediate and
+// expert, strategies to all of them and threads to one per core. Prints a table, and
+// writes the summaries as CSV or JSON when asked
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let games = option_value(&args, "--games")
+        .map(|v| v.parse().expect("Invalid game count"))
+        .unwrap_or(GAMES);
+    let seed = option_value(&args, "--seed")
+        .map(|v| v.parse().expect("Invalid seed"))
+        .unwrap_or(SEED);
+    let threads = option_value(&args, "--threads")
+        .map(|v| v.parse().expect("Invalid thread count"))
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+
+    let mut configurations: Vec<Configuration> = option_values(&args, "--config")
+        .iter()
+        .map(|v| parse_configuration(v).expect("Configurations look like 30x16x99"))
+        .collect();
+    if configurations.is_empty() {
+        configurations = vec![
+            parse_configuration("9x9x10").unwrap(),
+            parse_configuration("16x16x40").unwrap(),
+            parse_configuration("30x16x99").unwrap(),
+        ];
+    }
+
+    let all: Vec<Box<dyn Strategy>> = vec![
+        Box::new(RandomClicker),
+        Box::new(SolverPlayer),
+        Box::new(Autosolver::default()),
+    ];
+    let names = option_values(&args, "--strategy");
+    let strategies: Vec<&dyn Strategy> = all
+        .iter()
+        .map(|s| s.as_ref())
+        .filter(|s| names.is_empty() || names.contains(&s.name()))
+        .collect();
+    if strategies.is_empty() {
+        panic!("Unknown strategy");
+    }
+
+    println!(
+        "{:<10} {:>10} {:>6} {:>18} {:>15} {:>7} {:>7} {:>6}",
+        "strategy", "board", "games", "win rate (95%)", "guesses", "3BV", "clicks", "3BV/cl"
+    );
+    let mut summaries = Vec::new();
+    for &configuration in configurations.iter() {
+        if configuration.mine_count > configuration.width * configuration.height {
+            panic!("Configuration holds too many mines");
+        }
+        for strategy in strategies.iter() {
+            let records = simulation::simulate(configuration, *strategy, games, seed, threads);
+            let summary = simulation::summarize(strategy.name(), configuration, &records);
+            println!(
+                "{:<10} {:>10} {:>6} {:>6.1}% ({:>4.1}-{:>4.1}) {:>8.2} ±{:<5.2} {:>7.1} {:>7.1} {:>6.3}",
+                summary.strategy,
+                format!(
+                    "{}x{}/{}",
+                    configuration.width, configuration.height, configuration.mine_count
+                ),
+                summary.games,
+                summary.win_rate * 100.0,
+                summary.win_low * 100.0,
+                summary.win_high * 100.0,
+                summary.mean_guesses,
+                summary.guesses_margin,
+                summary.mean_bbbv,
+                summary.mean_clicks,
+                summary.efficiency
+            );
+            summaries.push(summary);
+        }
+    }
+
+    if let Some(path) = option_value(&args, "--csv") {
+        let mut text = simulation::CSV_HEADER.to_string() + "\n";
+        for summary in summaries.iter() {
+            text += &(summary.csv_row() + "\n");
+        }
+        fs::write(path, text).expect("Could not write CSV");
+    }
+    if let Some(path) = option_value(&args, "--json") {
+        let rows: Vec<String> = summaries
+            .iter()
+            .map(|s| format!("  {}", s.json()))
+            .collect();
+        fs::write(path, format!("[\n{}\n]\n", rows.join(",\n"))).expect("Could not write JSON");
+    }
+}
+
+// Width, height and mine count from WxHxM
+fn parse_configuration(text: &str) -> Option<Configuration> {
+    let parts: Vec<i32> = text
+        .split('x')
+        .map(|p| p.parse().ok())
+        .collect::<Option<Vec<i32>>>()?;
+    match parts[..] {
+        [width, height, mine_count] if width > 0 && height > 0 && mine_count >= 0 => {
+            Some(Configuration {
+                width,
+                height,
+                mine_count,
+            })
+        }
+        _ => None,
+    }
+}
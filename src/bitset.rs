@@ -0,0 +1,42 @@
+// A fixed number of bits packed 64 to a word
+#[derive(Clone, PartialEq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> Self {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn get(&self, n: usize) -> bool {
+        self.words[n / 64] & (1 << (n % 64)) != 0
+    }
+
+    pub fn set(&mut self, n: usize, value: bool) {
+        if value {
+            self.words[n / 64] |= 1 << (n % 64);
+        } else {
+            self.words[n / 64] &= !(1 << (n % 64));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    // Bits set here and not in other
+    pub fn count_without(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a & !b).count_ones() as usize)
+            .sum()
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+// One line per day: date, seed, result, seconds and 3BV
+pub const RESULTS_FILE: &str = "daily_results.txt";
+
+// How the day's one ranked attempt went
+pub struct DailyResult {
+    pub date: String,
+    pub seed: u64,
+    // "won", "lost", or "unfinished" for a game that was started and never ended
+    pub result: String,
+    pub seconds: f64,
+    pub bbbv: i32,
+}
+
+impl DailyResult {
+    // A few lines to paste into chat
+    pub fn share_text(&self) -> String {
+        let outcome = match self.result.as_str() {
+            "won" => format!("Won in {:.1}s", self.seconds),
+            "lost" => format!("Lost after {:.1}s", self.seconds),
+            _ => "Unfinished".to_string(),
+        };
+        let speed = if self.result == "won" && self.seconds > 0.0 {
+            format!("{:.2}", self.bbbv as f64 / self.seconds)
+        } else {
+            "-".to_string()
+        };
+        format!(
+            "Minesweeper daily {}\n{}\n3BV {}, {} 3BV/s\nSeed {}",
+            self.date, outcome, self.bbbv, speed, self.seed
+        )
+    }
+}
+
+// Today's date in UTC as YYYY-MM-DD
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let [year, month, day] = civil_from_days((seconds / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The seed every player uses for a date, which is just its digits, so 2024-03-01
+// plays seed 20240301
+pub fn seed_for(date: &str) -> Option<u64> {
+    let digits: String = date.chars().filter(|c| *c != '-').collect();
+    if date.len() != 10 || digits.len() != 8 {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+// The generator the day's board is built with. StdRng may change algorithm in any rand
+// release, while ChaCha8 gives the same numbers for a seed in every version, so
+// everyone gets the same board whatever they built with
+pub fn rng_for(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+pub fn load_results(path: &Path) -> Vec<DailyResult> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts[..] {
+                [date, seed, result, seconds, bbbv] => Some(DailyResult {
+                    date: date.to_string(),
+                    seed: seed.parse().ok()?,
+                    result: result.to_string(),
+                    seconds: seconds.parse().ok()?,
+                    bbbv: bbbv.parse().ok()?,
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// Adds the result, replacing any earlier line for the same date
+pub fn save_result(path: &Path, result: &DailyResult) -> io::Result<()> {
+    let mut results = load_results(path);
+    results.retain(|r| r.date != result.date);
+
+    let mut text = String::new();
+    for r in results.iter().chain(std::iter::once(result)) {
+        text += &format!(
+            "{} {} {} {:.3} {}\n",
+            r.date, r.seed, r.result, r.seconds, r.bbbv
+        );
+    }
+    fs::write(path, text)
+}
+
+// Year, month and day of a count of days since 1970-01-01
+fn civil_from_days(days: i64) -> [i64; 3] {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    [year, month, day]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minesweeper;
+    use crate::minesweeper::ClueRule;
+    use crate::placement::Placement;
+    use crate::topology::{SquareGrid, Topology};
+
+    #[test]
+    fn seed_is_the_date_digits() {
+        assert_eq!(seed_for("2024-02-29"), Some(20240229));
+        assert_eq!(seed_for("1999-12-31"), Some(19991231));
+    }
+
+    #[test]
+    fn malformed_dates_have_no_seed() {
+        for date in [
+            "",
+            "2024-2-29",
+            "20240229",
+            "2024/02/29",
+            "2024-02-2x",
+            "12024-02-29",
+        ] {
+            assert_eq!(seed_for(date), None, "{}", date);
+        }
+    }
+
+    #[test]
+    fn days_to_dates() {
+        assert_eq!(civil_from_days(0), [1970, 1, 1]);
+        assert_eq!(civil_from_days(-1), [1969, 12, 31]);
+        assert_eq!(civil_from_days(19_782), [2024, 2, 29]);
+        assert_eq!(civil_from_days(11_016), [2000, 2, 29]);
+        // 2100 is not a leap year
+        assert_eq!(civil_from_days(47_540), [2100, 2, 28]);
+        assert_eq!(civil_from_days(47_541), [2100, 3, 1]);
+    }
+
+    #[test]
+    fn daily_board_never_changes() {
+        // Everyone must get this board for the date, so a failure here means a change
+        // of generator or of mine placement, which would split the day's players
+        let topology = SquareGrid::new(9, 9);
+        let board = minesweeper::build_minesweeper_board(
+            &topology,
+            10,
+            1,
+            ClueRule::Standard,
+            &Placement::default(),
+            &mut rng_for(seed_for("2024-02-29").unwrap()),
+        )
+        .unwrap();
+        let mines: Vec<[i32; 2]> = topology
+            .positions()
+            .into_iter()
+            .filter(|[x, y]| board.has_mine(*x, *y))
+            .collect();
+        assert_eq!(
+            mines,
+            vec![
+                [8, 0],
+                [4, 2],
+                [5, 2],
+                [0, 3],
+                [4, 3],
+                [0, 4],
+                [2, 4],
+                [7, 5],
+                [3, 6],
+                [8, 6]
+            ]
+        );
+    }
+}
@@ -0,0 +1,582 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::minesweeper;
+use crate::minesweeper::{ClueRule, MinesweeperBoard};
+use crate::placement::Placement;
+use crate::solver;
+use crate::topology::SquareGrid;
+use crate::visible::VisibleBoard;
+
+// Highest number a square board can show
+const MAX_CLUE: usize = 8;
+
+// How a board is shown to an agent. Both only use what a player can see
+#[derive(Copy, Clone, PartialEq)]
+pub enum Encoding {
+    // One plane: the number on revealed tiles, -1 on hidden tiles and -2 on flags
+    Visible,
+    // A plane for each number 0 to 8, then one for hidden tiles and one for flags,
+    // each 1.0 where it applies and 0.0 elsewhere
+    OneHot,
+}
+
+impl Encoding {
+    pub fn planes(&self) -> usize {
+        match self {
+            Encoding::Visible => 1,
+            Encoding::OneHot => MAX_CLUE + 3,
+        }
+    }
+}
+
+// Reward for each outcome of a step. Shaping terms can be set to 0.0 for the sparse
+// win/loss signal
+#[derive(Copy, Clone)]
+pub struct Rewards {
+    pub win: f32,
+    pub loss: f32,
+    // For each safe tile a step opens
+    pub per_tile: f32,
+    // For a masked action, which changes nothing
+    pub invalid: f32,
+    // For revealing a tile the numbers didn't prove safe. Costs a solver run per
+    // reveal when not 0.0
+    pub guess: f32,
+}
+
+impl Rewards {
+    pub fn sparse() -> Self {
+        Rewards {
+            win: 1.0,
+            loss: -1.0,
+            per_tile: 0.0,
+            invalid: 0.0,
+            guess: 0.0,
+        }
+    }
+
+    // Opening the whole board is worth as much as winning
+    pub fn shaped(safe_tiles: i32) -> Self {
+        Rewards {
+            win: 1.0,
+            loss: -1.0,
+            per_tile: 1.0 / safe_tiles.max(1) as f32,
+            invalid: -0.1,
+            guess: -0.1,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Config {
+    pub width: i32,
+    pub height: i32,
+    pub mine_count: i32,
+    pub encoding: Encoding,
+    pub rewards: Rewards,
+    // Adds an action per tile that toggles a flag
+    pub flags: bool,
+}
+
+impl Config {
+    pub fn new(width: i32, height: i32, mine_count: i32) -> Self {
+        Config {
+            width,
+            height,
+            mine_count,
+            encoding: Encoding::OneHot,
+            rewards: Rewards::sparse(),
+            flags: false,
+        }
+    }
+
+    pub fn tiles(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    // Actions 0 to tiles - 1 reveal tile y * width + x, and with flags on, the next
+    // tiles toggle a flag on the same tiles
+    pub fn action_count(&self) -> usize {
+        self.tiles() * (1 + self.flags as usize)
+    }
+
+    pub fn observation_len(&self) -> usize {
+        self.encoding.planes() * self.tiles()
+    }
+}
+
+// What the agent sees after a reset or step
+pub struct Observation {
+    // planes x height x width values, row after row within each plane
+    pub data: Vec<f32>,
+    // true for each action that would change the board
+    pub action_mask: Vec<bool>,
+}
+
+pub struct Info {
+    pub won: bool,
+    pub lost: bool,
+    // The action was masked and did nothing
+    pub invalid: bool,
+    pub guessed: bool,
+    pub revealed: i32,
+    pub steps: i32,
+    pub bbbv: i32,
+}
+
+// One game an agent plays by calling reset and then step until done
+pub struct Environment {
+    config: Config,
+    topology: SquareGrid,
+    board: MinesweeperBoard,
+    // What the agent sees of board, updated after every step
+    view: VisibleBoard,
+    safe_tiles: i32,
+    bbbv: i32,
+    steps: i32,
+    done: bool,
+    won: bool,
+}
+
+impl Environment {
+    // Panics if the board has no tiles or the mines don't fit on it
+    pub fn new(config: Config) -> Self {
+        assert!(
+            config.width > 0 && config.height > 0,
+            "Invalid board size {}x{}, it needs at least one tile",
+            config.width,
+            config.height
+        );
+        let mut environment = Environment {
+            config,
+            topology: SquareGrid::new(config.width, config.height),
+            board: MinesweeperBoard::new(config.width, config.height),
+            view: VisibleBoard::hidden(config.width, config.height),
+            safe_tiles: config.width * config.height - config.mine_count,
+            bbbv: 0,
+            steps: 0,
+            done: true,
+            won: false,
+        };
+        environment.reset(0);
+        environment
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Starts the game for seed, which is the same board every time
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.board = minesweeper::build_minesweeper_board(
+            &self.topology,
+            self.config.mine_count,
+            1,
+            ClueRule::Standard,
+            &Placement::default(),
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .expect("Mine count doesn't fit the board");
+        self.view = VisibleBoard::new(&self.board);
+        self.bbbv = minesweeper::three_bv(&self.board, &self.topology);
+        self.steps = 0;
+        self.done = false;
+        self.won = false;
+        self.observe()
+    }
+
+    // Makes one move, returning what the agent now sees, the reward, whether the game
+    // is over and details of the step. Steps after the game is over do nothing
+    pub fn step(&mut self, action: usize) -> (Observation, f32, bool, Info) {
+        let rewards = self.config.rewards;
+        let tiles = self.config.tiles();
+        let mut info = Info {
+            won: false,
+            lost: false,
+            invalid: false,
+            guessed: false,
+            revealed: 0,
+            steps: self.steps,
+            bbbv: self.bbbv,
+        };
+        if self.done || action >= self.config.action_count() || !self.allowed(action) {
+            info.invalid = true;
+            info.won = self.won;
+            info.lost = self.done && !self.won;
+            let reward = if self.done { 0.0 } else { rewards.invalid };
+            return (self.observe(), reward, self.done, info);
+        }
+
+        self.steps += 1;
+        info.steps = self.steps;
+        let (x, y) = (
+            (action % tiles) as i32 % self.config.width,
+            (action % tiles) as i32 / self.config.width,
+        );
+        let mut reward = 0.0;
+
+        if action >= tiles {
+            minesweeper::flag_tile(&mut self.board, x, y, 0, false, 1);
+        } else {
+            if rewards.guess != 0.0 {
+                let deductions = solver::deduce(
+                    &self.view,
+                    &self.topology,
+                    ClueRule::Standard,
+                    1,
+                    self.config.mine_count,
+                );
+                info.guessed = !deductions.safe.contains(&[x, y]);
+                if info.guessed {
+                    reward += rewards.guess;
+                }
+            }
+
+            match minesweeper::reveal_tile(&mut self.board, &self.topology, x, y) {
+                Ok(opened) => {
+                    info.revealed = opened.len() as i32;
+                    reward += rewards.per_tile * opened.len() as f32;
+                }
+                Err(_) => {
+                    self.done = true;
+                    info.lost = true;
+                    reward += rewards.loss;
+                }
+            }
+        }
+
+        if !self.done && minesweeper::revealed_safe_tiles(&self.board) == self.safe_tiles {
+            self.done = true;
+            self.won = true;
+            info.won = true;
+            reward += rewards.win;
+        }
+        self.view = VisibleBoard::new(&self.board);
+        (self.observe(), reward, self.done, info)
+    }
+
+    // Whether an action would change the board: reveals of hidden, unflagged tiles and
+    // flags on hidden tiles
+    pub fn allowed(&self, action: usize) -> bool {
+        let tiles = self.config.tiles();
+        let n = (action % tiles) as i32;
+        let (x, y) = (n % self.config.width, n / self.config.width);
+        !self.view.is_revealed(x, y) && (action >= tiles || !self.view.is_flagged(x, y))
+    }
+
+    pub fn observe(&self) -> Observation {
+        let mut data = vec![0.0; self.config.observation_len()];
+        self.encode(&mut data);
+        Observation {
+            data,
+            action_mask: (0..self.config.action_count())
+                .map(|a| !self.done && self.allowed(a))
+                .collect(),
+        }
+    }
+
+    // Writes the observation into out, which is observation_len long
+    pub fn encode(&self, out: &mut [f32]) {
+        let tiles = self.config.tiles();
+        for n in 0..tiles {
+            let (x, y) = (n as i32 % self.config.width, n as i32 / self.config.width);
+            let revealed = self.view.is_revealed(x, y);
+            let flagged = self.view.is_flagged(x, y);
+            match self.config.encoding {
+                Encoding::Visible => {
+                    out[n] = if revealed {
+                        self.view.get_num(x, y) as f32
+                    } else if flagged {
+                        -2.0
+                    } else {
+                        -1.0
+                    };
+                }
+                Encoding::OneHot => {
+                    for plane in 0..self.config.encoding.planes() {
+                        out[plane * tiles + n] = 0.0;
+                    }
+                    let plane = if revealed {
+                        (self.view.get_num(x, y) as usize).min(MAX_CLUE)
+                    } else if flagged {
+                        MAX_CLUE + 2
+                    } else {
+                        MAX_CLUE + 1
+                    };
+                    out[plane * tiles + n] = 1.0;
+                }
+            }
+        }
+    }
+}
+
+// The result of stepping every environment in a batch, each field laid out one
+// environment after another
+pub struct BatchStep {
+    pub observations: Vec<f32>,
+    pub action_masks: Vec<bool>,
+    pub rewards: Vec<f32>,
+    pub dones: Vec<bool>,
+    pub wins: Vec<bool>,
+}
+
+// Many environments stepped together. A finished game is replaced by the next seed's
+// straight away, so the observation returned with done is already the new game's
+pub struct VecEnvironment {
+    environments: Vec<Environment>,
+    next_seed: u64,
+}
+
+impl VecEnvironment {
+    pub fn new(config: Config, count: usize) -> Self {
+        VecEnvironment {
+            environments: (0..count).map(|_| Environment::new(config)).collect(),
+            next_seed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.environments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.environments.is_empty()
+    }
+
+    // Starts the nth environment on seed + n
+    pub fn reset(&mut self, seed: u64) -> (Vec<f32>, Vec<bool>) {
+        let mut observations = Vec::new();
+        let mut action_masks = Vec::new();
+        for (n, environment) in self.environments.iter_mut().enumerate() {
+            let observation = environment.reset(seed + n as u64);
+            observations.extend(observation.data);
+            action_masks.extend(observation.action_mask);
+        }
+        self.next_seed = seed + self.environments.len() as u64;
+        (observations, action_masks)
+    }
+
+    // Steps environment n with actions[n]. Panics unless there is exactly one action
+    // per environment, since a batch of the wrong length would misalign every row
+    pub fn step(&mut self, actions: &[usize]) -> BatchStep {
+        assert_eq!(
+            actions.len(),
+            self.environments.len(),
+            "One action is needed per environment"
+        );
+        let mut batch = BatchStep {
+            observations: Vec::new(),
+            action_masks: Vec::new(),
+            rewards: Vec::new(),
+            dones: Vec::new(),
+            wins: Vec::new(),
+        };
+        for (environment, &action) in self.environments.iter_mut().zip(actions.iter()) {
+            let (mut observation, reward, done, info) = environment.step(action);
+            if done {
+                observation = environment.reset(self.next_seed);
+                self.next_seed += 1;
+            }
+            batch.observations.extend(observation.data);
+            batch.action_masks.extend(observation.action_mask);
+            batch.rewards.push(reward);
+            batch.dones.push(done);
+            batch.wins.push(info.won);
+        }
+        batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A game on a one row board with mines where the row has '*'
+    fn environment(row: &str, config: Config) -> Environment {
+        let mut environment = Environment::new(config);
+        let mut board = MinesweeperBoard::new(row.len() as i32, 1);
+        for (x, c) in row.chars().enumerate() {
+            if c == '*' {
+                board.set_mine(x as i32, 0);
+            }
+        }
+        minesweeper::determine_tile_number(
+            &mut board,
+            &environment.topology,
+            ClueRule::Standard,
+            1,
+            &mut StdRng::seed_from_u64(0),
+        );
+        environment.view = VisibleBoard::new(&board);
+        environment.board = board;
+        environment
+    }
+
+    fn rewards() -> Rewards {
+        Rewards {
+            win: 10.0,
+            loss: -10.0,
+            per_tile: 1.0,
+            invalid: -0.5,
+            guess: -2.0,
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid board size 0x5")]
+    fn zero_width_is_rejected() {
+        Environment::new(Config::new(0, 5, 0));
+    }
+
+    #[test]
+    fn action_mask_follows_the_board() {
+        let config = Config {
+            flags: true,
+            ..Config::new(5, 1, 2)
+        };
+        let mut environment = environment("*.*..", config);
+        assert_eq!(environment.observe().action_mask, vec![true; 10]);
+
+        // Opens the 0 and the 1 beside it
+        environment.step(4);
+        let mask = environment.observe().action_mask;
+        assert_eq!(mask, [[true, true, true, false, false]; 2].concat());
+
+        // A flagged tile can't be revealed, but the flag can come off
+        environment.step(5);
+        let mask = environment.observe().action_mask;
+        assert_eq!(
+            mask,
+            [
+                [false, true, true, false, false],
+                [true, true, true, false, false]
+            ]
+            .concat()
+        );
+
+        environment.step(5);
+        environment.step(0);
+        assert!(environment.observe().action_mask.iter().all(|a| !a));
+    }
+
+    #[test]
+    fn visible_encoding() {
+        let config = Config {
+            encoding: Encoding::Visible,
+            flags: true,
+            ..Config::new(5, 1, 2)
+        };
+        let mut environment = environment("*.*..", config);
+        assert_eq!(environment.observe().data, vec![-1.0; 5]);
+        environment.step(4);
+        assert_eq!(environment.observe().data, vec![-1.0, -1.0, -1.0, 1.0, 0.0]);
+        environment.step(5);
+        assert_eq!(environment.observe().data, vec![-2.0, -1.0, -1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn one_hot_encoding() {
+        let config = Config {
+            flags: true,
+            ..Config::new(5, 1, 2)
+        };
+        let mut environment = environment("*.*..", config);
+        environment.step(4);
+        environment.step(5);
+        let data = environment.observe().data;
+        assert_eq!(data.len(), config.observation_len());
+
+        let planes = |n: usize| -> Vec<usize> {
+            (0..Encoding::OneHot.planes())
+                .filter(|plane| data[plane * 5 + n] == 1.0)
+                .collect()
+        };
+        assert_eq!(planes(0), vec![MAX_CLUE + 2]);
+        assert_eq!(planes(1), vec![MAX_CLUE + 1]);
+        assert_eq!(planes(2), vec![MAX_CLUE + 1]);
+        assert_eq!(planes(3), vec![1]);
+        assert_eq!(planes(4), vec![0]);
+        assert_eq!(data.iter().sum::<f32>(), 5.0);
+    }
+
+    #[test]
+    fn shaped_rewards() {
+        let config = Config {
+            rewards: rewards(),
+            ..Config::new(4, 1, 1)
+        };
+        let mut environment = environment("*...", config);
+
+        // Nothing is proven on a blank board, so the first reveal is a guess
+        let (_, reward, done, info) = environment.step(1);
+        assert_eq!((reward, done), (1.0 - 2.0, false));
+        assert!(info.guessed && !info.invalid);
+        assert_eq!(info.revealed, 1);
+
+        let (_, reward, done, info) = environment.step(1);
+        assert_eq!((reward, done), (-0.5, false));
+        assert!(info.invalid);
+
+        // With the 1 showing, the mine count proves the far tile safe
+        let (_, reward, done, info) = environment.step(3);
+        assert_eq!((reward, done), (2.0 + 10.0, true));
+        assert!(info.won && !info.guessed);
+        assert_eq!(info.steps, 2);
+
+        // Steps after the end change nothing and score nothing
+        let (_, reward, done, info) = environment.step(0);
+        assert_eq!((reward, done), (0.0, true));
+        assert!(info.invalid && info.won);
+    }
+
+    #[test]
+    fn losing_reward() {
+        let config = Config {
+            rewards: rewards(),
+            ..Config::new(4, 1, 1)
+        };
+        let mut environment = environment("*...", config);
+        let (observation, reward, done, info) = environment.step(0);
+        assert_eq!((reward, done), (-10.0 - 2.0, true));
+        assert!(info.lost && !info.won);
+        assert!(observation.action_mask.iter().all(|a| !a));
+    }
+
+    #[test]
+    fn batch_resets_finished_games_on_the_next_seeds() {
+        // Any first reveal on a 3x3 board with 8 mines ends the game
+        let config = Config::new(3, 3, 8);
+        let board_for = |seed| {
+            let mut environment = Environment::new(config);
+            environment.reset(seed);
+            environment.board
+        };
+        let mut batch = VecEnvironment::new(config, 2);
+
+        let (observations, masks) = batch.reset(10);
+        assert_eq!(observations.len(), 2 * config.observation_len());
+        assert_eq!(masks.len(), 2 * config.action_count());
+        assert!(batch.environments[0].board == board_for(10));
+        assert!(batch.environments[1].board == board_for(11));
+
+        let step = batch.step(&[0, 4]);
+        assert_eq!(step.dones, vec![true, true]);
+        assert_eq!(step.rewards.len(), 2);
+        assert_eq!(
+            step.wins,
+            vec![!board_for(10).has_mine(0, 0), !board_for(11).has_mine(1, 1)]
+        );
+        assert!(batch.environments[0].board == board_for(12));
+        assert!(batch.environments[1].board == board_for(13));
+        // The observations are already the new games'
+        assert!(step.action_masks.iter().all(|a| *a));
+        assert_eq!(batch.next_seed, 14);
+    }
+
+    #[test]
+    #[should_panic(expected = "One action is needed per environment")]
+    fn batch_needs_one_action_per_environment() {
+        VecEnvironment::new(Config::new(3, 3, 1), 2).step(&[0]);
+    }
+}
@@ -0,0 +1,197 @@
+// C bindings, exported from the cdylib. include/minesweeper.h declares them and is
+// regenerated from this file with:
+//
+//   cargo build --features c-header
+//
+// Every call catches panics so none unwind into the caller, returning MS_ERROR_PANIC
+// (or NULL) instead. Functions taking a game are unsafe, since they trust the pointer
+// to be NULL or a live game from ms_game_new
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+use crate::game::{Game, Status};
+use crate::visible::VisibleTile;
+
+// Returned by moves and queries that fail
+pub const MS_ERROR_NULL: i32 = -1;
+pub const MS_ERROR_OFF_BOARD: i32 = -2;
+pub const MS_ERROR_PANIC: i32 = -3;
+
+// ms_game_status
+pub const MS_STATUS_PLAYING: i32 = 0;
+pub const MS_STATUS_LOST: i32 = 1;
+pub const MS_STATUS_WON: i32 = 2;
+
+// ms_game_tile gives the number on revealed tiles, or one of these
+pub const MS_TILE_HIDDEN: i32 = 9;
+pub const MS_TILE_FLAGGED: i32 = 10;
+pub const MS_TILE_QUESTIONED: i32 = 11;
+// Once the game is over
+pub const MS_TILE_MINE: i32 = 12;
+pub const MS_TILE_EXPLODED: i32 = 13;
+pub const MS_TILE_WRONG_FLAG: i32 = 14;
+
+// A game, only ever used through a pointer from ms_game_new
+pub struct MsGame {
+    game: Game,
+}
+
+fn guard<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+// Runs f on the game at (x, y) after checking both. game must be NULL or a live
+// pointer from ms_game_new with no other reference to it
+unsafe fn with_tile(game: *mut MsGame, x: i32, y: i32, f: impl FnOnce(&mut Game) -> i32) -> i32 {
+    guard(MS_ERROR_PANIC, || {
+        // Safety: upheld by the caller
+        let Some(game) = (unsafe { game.as_mut() }) else {
+            return MS_ERROR_NULL;
+        };
+        if !game.game.contains(x, y) {
+            return MS_ERROR_OFF_BOARD;
+        }
+        f(&mut game.game)
+    })
+}
+
+// Runs f on the game. game must be NULL or a live pointer from ms_game_new
+unsafe fn with_game(game: *const MsGame, f: impl FnOnce(&Game) -> i32) -> i32 {
+    guard(MS_ERROR_PANIC, || {
+        // Safety: upheld by the caller
+        match unsafe { game.as_ref() } {
+            Some(game) => f(&game.game),
+            None => MS_ERROR_NULL,
+        }
+    })
+}
+
+/// Starts a game. The same seed always gives the same board. Returns NULL if the
+/// mines don't fit on the board
+#[no_mangle]
+pub extern "C" fn ms_game_new(width: i32, height: i32, mines: i32, seed: u64) -> *mut MsGame {
+    guard(std::ptr::null_mut(), || {
+        if width <= 0 || height <= 0 || mines < 0 {
+            return std::ptr::null_mut();
+        }
+        match Game::seeded(width, height, mines, 1, seed) {
+            Ok(game) => Box::into_raw(Box::new(MsGame { game })),
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Frees a game from ms_game_new. NULL is ignored.
+///
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new, and is not used again
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_free(game: *mut MsGame) {
+    guard((), || {
+        if !game.is_null() {
+            // Safety: upheld by the caller
+            drop(unsafe { Box::from_raw(game) });
+        }
+    })
+}
+
+/// Reveals the tile, returning how many tiles opened. Revealing a mine loses the game
+///
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new, not used by another thread
+/// during the call
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_reveal(game: *mut MsGame, x: i32, y: i32) -> i32 {
+    with_tile(game, x, y, |game| game.reveal(x, y).len() as i32)
+}
+
+/// Reveals the neighbours of a revealed number with as many flags around it,
+/// returning how many tiles opened
+///
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new, not used by another thread
+/// during the call
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_chord(game: *mut MsGame, x: i32, y: i32) -> i32 {
+    with_tile(game, x, y, |game| game.chord(x, y).len() as i32)
+}
+
+/// Toggles a flag on a hidden tile, returning 1 if it is now flagged and 0 if not
+///
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new, not used by another thread
+/// during the call
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_flag(game: *mut MsGame, x: i32, y: i32) -> i32 {
+    with_tile(game, x, y, |game| game.flag(x, y))
+}
+
+/// What the player sees on the tile: its number 0 to 8 once revealed, otherwise
+/// MS_TILE_HIDDEN, MS_TILE_FLAGGED or MS_TILE_QUESTIONED. Once the game is over,
+/// unflagged mines show as MS_TILE_MINE, the one that was hit as MS_TILE_EXPLODED and
+/// flags on safe tiles as MS_TILE_WRONG_FLAG
+///
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_tile(game: *const MsGame, x: i32, y: i32) -> i32 {
+    with_game(game, |game| {
+        if !game.contains(x, y) {
+            return MS_ERROR_OFF_BOARD;
+        }
+        match game.view().tile(x, y) {
+            VisibleTile::Revealed(num) => num,
+            VisibleTile::Hidden => MS_TILE_HIDDEN,
+            VisibleTile::Flagged(_) => MS_TILE_FLAGGED,
+            VisibleTile::Questioned => MS_TILE_QUESTIONED,
+            VisibleTile::Mine(_) => MS_TILE_MINE,
+            VisibleTile::Exploded => MS_TILE_EXPLODED,
+            VisibleTile::WrongFlag => MS_TILE_WRONG_FLAG,
+        }
+    })
+}
+
+/// MS_STATUS_PLAYING, MS_STATUS_LOST or MS_STATUS_WON
+///
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_status(game: *const MsGame) -> i32 {
+    with_game(game, |game| match game.status() {
+        Status::Playing => MS_STATUS_PLAYING,
+        Status::Lost => MS_STATUS_LOST,
+        Status::Won => MS_STATUS_WON,
+    })
+}
+
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_width(game: *const MsGame) -> i32 {
+    with_game(game, |game| game.width())
+}
+
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_height(game: *const MsGame) -> i32 {
+    with_game(game, |game| game.height())
+}
+
+/// Mines less flags placed, as the counter shows
+///
+/// # Safety
+///
+/// game must be NULL or a live pointer from ms_game_new
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_mines_left(game: *const MsGame) -> i32 {
+    with_game(game, |game| game.mines_left())
+}
@@ -0,0 +1,188 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::minesweeper;
+use crate::minesweeper::{ClueRule, GenerationError, MinesweeperBoard};
+use crate::placement::Placement;
+use crate::topology::{SquareGrid, Topology};
+use crate::visible::VisibleBoard;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Status {
+    Playing,
+    Lost,
+    Won,
+}
+
+// A single player game on a square board under the standard rules, tracking the mine
+// counter and how the game ended. This is what the bindings for other languages wrap
+pub struct Game {
+    board: MinesweeperBoard,
+    topology: SquareGrid,
+    mine_count: i32,
+    max_per_tile: i32,
+    mines_left: i32,
+    safe_tiles: i32,
+    status: Status,
+    hit_mine: Option<[i32; 2]>,
+}
+
+impl Game {
+    pub fn new(
+        width: i32,
+        height: i32,
+        mine_count: i32,
+        max_per_tile: i32,
+        rng: &mut impl Rng,
+    ) -> Result<Self, GenerationError> {
+        let topology = SquareGrid::new(width.max(0), height.max(0));
+        let board = minesweeper::build_minesweeper_board(
+            &topology,
+            mine_count,
+            max_per_tile,
+            ClueRule::Standard,
+            &Placement::default(),
+            rng,
+        )?;
+        let safe_tiles =
+            width.max(0) * height.max(0) - board.tiles().filter(|t| t.has_mine()).count() as i32;
+
+        Ok(Game {
+            board,
+            topology,
+            mine_count,
+            max_per_tile,
+            mines_left: mine_count,
+            safe_tiles,
+            status: Status::Playing,
+            hit_mine: None,
+        })
+    }
+
+    // The same seed always gives the same board
+    pub fn seeded(
+        width: i32,
+        height: i32,
+        mine_count: i32,
+        max_per_tile: i32,
+        seed: u64,
+    ) -> Result<Self, GenerationError> {
+        Game::new(
+            width,
+            height,
+            mine_count,
+            max_per_tile,
+            &mut StdRng::seed_from_u64(seed),
+        )
+    }
+
+    // What the player sees, with the mines shown once the game is over
+    pub fn view(&self) -> VisibleBoard {
+        match self.status {
+            Status::Playing => VisibleBoard::new(&self.board),
+            _ => VisibleBoard::after_game(&self.board, self.hit_mine),
+        }
+    }
+
+    pub fn topology(&self) -> &SquareGrid {
+        &self.topology
+    }
+
+    pub fn width(&self) -> i32 {
+        self.board.width()
+    }
+
+    pub fn height(&self) -> i32 {
+        self.board.height()
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.topology.contains(x, y)
+    }
+
+    pub fn mine_count(&self) -> i32 {
+        self.mine_count
+    }
+
+    pub fn max_per_tile(&self) -> i32 {
+        self.max_per_tile
+    }
+
+    // Mines less flags placed, as the counter shows
+    pub fn mines_left(&self) -> i32 {
+        self.mines_left
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    // The fewest clicks that clear the board
+    pub fn three_bv(&self) -> i32 {
+        minesweeper::three_bv(&self.board, &self.topology)
+    }
+
+    pub fn revealed_safe_tiles(&self) -> i32 {
+        minesweeper::revealed_safe_tiles(&self.board)
+    }
+
+    // The mine that ended a lost game
+    pub fn hit_mine(&self) -> Option<[i32; 2]> {
+        self.hit_mine
+    }
+
+    // Reveals the tile, returning the tiles opened. Does nothing once the game is over
+    // or off the board
+    pub fn reveal(&mut self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        if self.status != Status::Playing || !self.contains(x, y) {
+            return Vec::new();
+        }
+        let result = minesweeper::reveal_tile(&mut self.board, &self.topology, x, y);
+        self.finish_move(result)
+    }
+
+    // Reveals the neighbours of a revealed number with as many flags around it
+    pub fn chord(&mut self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        if self.status != Status::Playing || !self.contains(x, y) {
+            return Vec::new();
+        }
+        let result =
+            minesweeper::cord_tile(&mut self.board, &self.topology, ClueRule::Standard, x, y);
+        self.finish_move(result)
+    }
+
+    // Adds a flag to a hidden tile, or clears them once it holds max_per_tile,
+    // returning the flags now on it
+    pub fn flag(&mut self, x: i32, y: i32) -> i32 {
+        if !self.contains(x, y) {
+            return 0;
+        }
+        if self.status == Status::Playing {
+            self.mines_left = minesweeper::flag_tile(
+                &mut self.board,
+                x,
+                y,
+                self.mines_left,
+                false,
+                self.max_per_tile,
+            );
+        }
+        self.board.get_flags(x, y)
+    }
+
+    fn finish_move(&mut self, result: Result<Vec<[i32; 2]>, [i32; 2]>) -> Vec<[i32; 2]> {
+        match result {
+            Ok(opened) => {
+                if minesweeper::revealed_safe_tiles(&self.board) == self.safe_tiles {
+                    self.status = Status::Won;
+                }
+                opened
+            }
+            Err(mine) => {
+                self.status = Status::Lost;
+                self.hit_mine = Some(mine);
+                Vec::new()
+            }
+        }
+    }
+}
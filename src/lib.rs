@@ -0,0 +1,24 @@
+pub mod autosolver;
+pub mod bitset;
+pub mod daily;
+pub mod environment;
+pub mod ffi;
+pub mod game;
+#[cfg(feature = "gui")]
+pub mod mask;
+pub mod minesweeper;
+pub mod multiplayer;
+pub mod options;
+pub mod placement;
+pub mod puzzle;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod review;
+pub mod simulation;
+pub mod solver;
+pub mod topology;
+#[cfg(feature = "gui")]
+pub mod user_interface;
+pub mod visible;
+#[cfg(feature = "wasm")]
+pub mod wasm;
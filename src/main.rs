@@ -1,11 +1,253 @@
-use crate::user_interface::GUI;
+use std::path::Path;
 
-pub mod minesweeper;
-mod user_interface;
+use ::minesweeper::minesweeper::ClueRule;
+use ::minesweeper::multiplayer::{MinePenalty, Session};
+use ::minesweeper::options::option_value;
+use ::minesweeper::placement::Placement;
+use ::minesweeper::topology::{
+    CubeGrid, HexGrid, KnightGrid, MaskedGrid, SquareGrid, Topology, WrapGrid,
+};
+use ::minesweeper::user_interface::GUI;
+use ::minesweeper::{daily, mask, minesweeper, multiplayer, placement, puzzle};
 
+const HEIGHT: i32 = 16;
+const WIDTH: i32 = 30;
+const MINE_COUNT: i32 = 99;
+const DEFAULT_PORT: u16 = 7878;
+const CUBE_SIZE: i32 = 8;
+const CUBE_DEPTH: i32 = 5;
+const SHAPE_SIZE: i32 = 20;
+
+// Usage:
+//   minesweeper [--practice] [--hex | --wrap [--ghosts] | --cube [depth]]
+//                                            single player, practice allows undoing a lost game.
+//                                            Cube boards show one layer at a time, up/down
+//                                            arrows move between layers
+//   minesweeper host [--port <n>] [--lives <n>]
+//                                            start a shared board and play on it
+//   minesweeper join <address:port>          play on someone else's shared board
+//   minesweeper daily                        today's board, the same for everyone, with one
+//                                            ranked attempt
+//   minesweeper daily share [YYYY-MM-DD]     print a day's result to paste into chat
+//   minesweeper puzzles <pack>               play a pack of hand made puzzles
+//   minesweeper validate <pack>              check every puzzle in a pack can be solved by
+//                                            logic alone
+//
+// Single player also accepts --max-mines <n> to let a tile hold up to n mines, flagged by
+// right clicking it up to n times, and --rules <standard|knight|liar|split> to change what
+// the numbers count. N opens a menu to start a new game under different rules.
+// --mask <heart|ring|diamond|file> cuts the board into a shape, read from a text file
+// ('.' for holes) or a PNG (dark pixels for tiles). Mines can be spread with
+// --density <file> (rows of digits 0-9 stretched over the board), --cluster <-1..1>
+// (positive bunches mines together, negative spreads them out), --spacing <n> (steps
+// between mines) and --safe-border <n> (mine free ring along the edges).
+// --watch <moves per second> hands the board to the built-in player, which A toggles
+// during any single player game and +/- speed up or slow down. After losing a single
+// player game, V opens a review of where it went wrong. Any mode accepts
+// --no-question-marks to right click between flag and nothing only
 fn main() {
-    let board = minesweeper::build_minesweeper_board(16, 30, 99);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let question_marks = !args.iter().any(|a| a == "--no-question-marks");
+
+    match args.first().map(|a| a.as_str()) {
+        Some("host") => {
+            let mut port = DEFAULT_PORT;
+            let mut penalty = MinePenalty::EndGame;
+
+            let mut rest = args[1..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--no-question-marks" => {}
+                    "--port" => {
+                        port = rest
+                            .next()
+                            .and_then(|p| p.parse().ok())
+                            .expect("Invalid port");
+                    }
+                    "--lives" => {
+                        let lives = rest
+                            .next()
+                            .and_then(|n| n.parse().ok())
+                            .filter(|n: &i32| *n >= 1)
+                            .expect("Invalid lives, there must be at least 1");
+                        penalty = MinePenalty::LoseLife(lives);
+                    }
+                    _ => {
+                        eprintln!("Unknown option for host: {}", arg);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            multiplayer::host(port, HEIGHT, WIDTH, MINE_COUNT, penalty)
+                .expect("Could not start server");
+            play_online(&format!("127.0.0.1:{}", port), question_marks);
+        }
+        Some("join") => {
+            let addr = args.get(1).expect("Missing server address");
+            play_online(addr, question_marks);
+        }
+        Some("daily") if args.get(1).map(|a| a.as_str()) == Some("share") => {
+            let date = args.get(2).cloned().unwrap_or_else(daily::today);
+            match daily::load_results(Path::new(daily::RESULTS_FILE))
+                .iter()
+                .find(|r| r.date == date)
+            {
+                Some(result) => println!("{}", result.share_text()),
+                None => println!("No daily result for {}", date),
+            }
+        }
+        Some("daily") => {
+            let date = daily::today();
+            let seed = daily::seed_for(&date).expect("Invalid date");
+            let topology = SquareGrid::new(WIDTH, HEIGHT);
+            let board = minesweeper::build_minesweeper_board(
+                &topology,
+                MINE_COUNT,
+                1,
+                ClueRule::Standard,
+                &Placement::default(),
+                &mut daily::rng_for(seed),
+            )
+            .expect("Could not build board");
+
+            let mut gui: GUI = GUI::new(board, MINE_COUNT, Box::new(topology));
+            gui.set_question_marks(question_marks);
+            gui.set_daily(date, seed);
+            gui.run();
+        }
+        Some("puzzles") => {
+            let path = Path::new(args.get(1).expect("Missing puzzle pack"));
+            let pack = puzzle::load_pack(path).expect("Could not load puzzle pack");
+
+            let mut gui: GUI = GUI::puzzles(pack, puzzle::progress_path(path));
+            gui.set_question_marks(question_marks);
+            gui.run();
+        }
+        Some("validate") => {
+            let path = Path::new(args.get(1).expect("Missing puzzle pack"));
+            let pack = puzzle::load_pack(path).expect("Could not load puzzle pack");
+
+            let mut failed = false;
+            for p in pack.puzzles.iter() {
+                match puzzle::validate(p) {
+                    Ok(()) => println!("OK   {}", p.name),
+                    Err(e) => {
+                        println!("FAIL {}: {}", p.name, e);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            let mut mine_count = MINE_COUNT;
+            let rules = match args.iter().position(|a| a == "--rules") {
+                Some(n) => args.get(n + 1).map(|r| r.as_str()).expect("Missing rules"),
+                None => "standard",
+            };
+            let clue_rule = match rules {
+                "standard" | "knight" => ClueRule::Standard,
+                "liar" => ClueRule::Liar,
+                "split" => ClueRule::ColourSplit,
+                _ => panic!("Unknown rules {}", rules),
+            };
+            let board_mask = args.iter().position(|a| a == "--mask").map(|n| {
+                let name = args.get(n + 1).expect("Missing mask");
+                mask::shape_mask(name, SHAPE_SIZE)
+                    .unwrap_or_else(|| mask::load_mask(Path::new(name)).expect("Invalid mask"))
+            });
+            let [width, height] = board_mask
+                .as_ref()
+                .map(mask::mask_dimensions)
+                .unwrap_or([WIDTH, HEIGHT]);
+
+            let mut topology: Box<dyn Topology> = if rules == "knight" {
+                assert!(
+                    !args
+                        .iter()
+                        .any(|a| ["--hex", "--wrap", "--cube"].contains(&a.as_str())),
+                    "Knight rules can't be combined with --hex, --wrap or --cube"
+                );
+                Box::new(KnightGrid::new(width, height))
+            } else if args.iter().any(|a| a == "--hex") {
+                Box::new(HexGrid::new(width, height))
+            } else if args.iter().any(|a| a == "--wrap") {
+                Box::new(WrapGrid::new(width, height))
+            } else if let Some(n) = args.iter().position(|a| a == "--cube") {
+                assert!(board_mask.is_none(), "Cube boards can't be masked");
+                let depth = args
+                    .get(n + 1)
+                    .and_then(|d| d.parse().ok())
+                    .unwrap_or(CUBE_DEPTH);
+                assert!(depth >= 1, "Invalid cube depth, it must be at least 1");
+                // About one cube in ten holds a mine
+                mine_count = CUBE_SIZE * CUBE_SIZE * depth / 10;
+                Box::new(CubeGrid::new(CUBE_SIZE, CUBE_SIZE, depth))
+            } else {
+                Box::new(SquareGrid::new(width, height))
+            };
+            if let Some(board_mask) = board_mask {
+                // Keep the density of the full size board
+                mine_count = mask::mask_tiles(&board_mask) * MINE_COUNT / (WIDTH * HEIGHT);
+                topology = Box::new(MaskedGrid::new(topology, board_mask));
+            }
+            let max_per_tile = match args.iter().position(|a| a == "--max-mines") {
+                Some(n) => args
+                    .get(n + 1)
+                    .and_then(|m| m.parse().ok())
+                    .expect("Invalid mines per tile"),
+                None => 1,
+            };
+            let placement = Placement {
+                density: option_value(&args, "--density")
+                    .map(|p| placement::load_density(Path::new(p)).expect("Invalid density map")),
+                clustering: option_value(&args, "--cluster")
+                    .map(|c| {
+                        c.parse()
+                            .ok()
+                            .filter(|c: &f64| (-1.0..=1.0).contains(c))
+                            .expect("Invalid clustering, it must be between -1 and 1")
+                    })
+                    .unwrap_or(0.0),
+                min_spacing: option_value(&args, "--spacing")
+                    .map(|s| s.parse().expect("Invalid spacing"))
+                    .unwrap_or(1),
+                safe_border: option_value(&args, "--safe-border")
+                    .map(|b| b.parse().expect("Invalid border"))
+                    .unwrap_or(0),
+            };
+            let board = minesweeper::build_minesweeper_board(
+                topology.as_ref(),
+                mine_count,
+                max_per_tile,
+                clue_rule,
+                &placement,
+                &mut rand::thread_rng(),
+            )
+            .expect("Could not build board");
+
+            let mut gui: GUI = GUI::new(board, mine_count, topology);
+            gui.set_practice(args.iter().any(|a| a == "--practice"));
+            gui.set_question_marks(question_marks);
+            gui.set_ghosts(args.iter().any(|a| a == "--ghosts"));
+            gui.set_max_mines_per_tile(max_per_tile);
+            gui.set_clue_rule(clue_rule);
+            gui.set_placement(placement);
+            if let Some(speed) = option_value(&args, "--watch") {
+                gui.set_watch(speed.parse().expect("Invalid watch speed"));
+            }
+            gui.run();
+        }
+    }
+}
+
+fn play_online(addr: &str, question_marks: bool) {
+    let (session, snapshot) = Session::connect(addr).expect("Could not connect to server");
 
-    let mut gui: GUI = GUI::new(board, 99);
+    let mut gui: GUI = GUI::join(session, snapshot);
+    gui.set_question_marks(question_marks);
     gui.run();
 }
@@ -0,0 +1,86 @@
+extern crate image;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Which tiles of a rectangle are part of the board, mask[y][x] being true for tiles
+// that exist. Every row is the same length
+pub type Mask = Vec<Vec<bool>>;
+
+// Reads a mask from a PNG, where opaque dark pixels are tiles, or from a text file,
+// where '.' and ' ' are holes and any other character is a tile
+pub fn load_mask(path: &Path) -> io::Result<Mask> {
+    let is_png = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("png"))
+        .unwrap_or(false);
+
+    let mask = if is_png {
+        let image = image::open(path)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            .to_luma_alpha8();
+        (0..image.height())
+            .map(|y| {
+                (0..image.width())
+                    .map(|x| {
+                        let [luma, alpha] = image.get_pixel(x, y).0;
+                        alpha >= 128 && luma < 128
+                    })
+                    .collect()
+            })
+            .collect()
+    } else {
+        let text = fs::read_to_string(path)?;
+        let rows: Vec<&str> = text.lines().collect();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        rows.iter()
+            .map(|row| {
+                let mut cells: Vec<bool> = row.chars().map(|c| c != '.' && c != ' ').collect();
+                cells.resize(width, false);
+                cells
+            })
+            .collect()
+    };
+
+    if mask_tiles(&mask) == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Mask has no tiles",
+        ));
+    }
+    Ok(mask)
+}
+
+// A built-in heart, ring or diamond drawn on a size x size board
+pub fn shape_mask(name: &str, size: i32) -> Option<Mask> {
+    let inside: fn(f64, f64) -> bool = match name {
+        "heart" => |u, v| {
+            let (x, y) = (u * 1.25, 0.2 - v * 1.25);
+            (x * x + y * y - 1.0).powi(3) - (x * x * y * y * y) <= 0.0
+        },
+        "ring" => |u, v| (0.45..=1.0).contains(&(u * u + v * v).sqrt()),
+        "diamond" => |u, v| u.abs() + v.abs() <= 1.0,
+        _ => return None,
+    };
+
+    // u and v run from -1 to 1 across the board, measured at tile centres
+    let scale = |n: i32| ((n as f64 + 0.5) / size as f64) * 2.0 - 1.0;
+    Some(
+        (0..size)
+            .map(|y| (0..size).map(|x| inside(scale(x), scale(y))).collect())
+            .collect(),
+    )
+}
+
+// Width and height of the rectangle a mask is cut from
+pub fn mask_dimensions(mask: &Mask) -> [i32; 2] {
+    [
+        mask.first().map(|r| r.len()).unwrap_or(0) as i32,
+        mask.len() as i32,
+    ]
+}
+
+pub fn mask_tiles(mask: &Mask) -> i32 {
+    mask.iter().flatten().filter(|t| **t).count() as i32
+}
@@ -1,319 +1,897 @@
 extern crate rand;
 
+use std::collections::HashMap;
+use std::fmt;
+
 use rand::Rng;
 
-#[derive(Copy, Clone)]
+use crate::bitset::BitSet;
+use crate::placement::{ChanceTree, Placement};
+use crate::topology::Topology;
+
+// One tile of a board, copied out of it. Changing a Tile doesn't change the board
+// until it is passed back to set_tile
+#[derive(Copy, Clone, PartialEq)]
 pub struct Tile {
-    mine: bool,
+    mines: i32,
     num: i32,
-    flagged: bool,
+    split: i32,
+    flags: i32,
+    questioned: bool,
     revealed: bool,
-    pos: [i32; 2],
 }
 
-pub type MinesweeperBoard = Vec<Vec<Tile>>;
-
 impl Tile {
     // no args constructor
     pub fn empty() -> Self {
         Tile {
-            mine: false,
+            mines: 0,
             num: 0,
-            flagged: false,
+            split: 0,
+            flags: 0,
+            questioned: false,
             revealed: false,
-            pos: [0, 0],
         }
     }
 
     // full args constructor
-    pub fn new(mine: bool, num: i32, flagged: bool, revealed: bool, pos: [i32; 2]) -> Self {
+    pub fn new(
+        mines: i32,
+        num: i32,
+        split: i32,
+        flags: i32,
+        questioned: bool,
+        revealed: bool,
+    ) -> Self {
         Tile {
-            mine,
+            mines,
             num,
-            flagged,
+            split,
+            flags,
+            questioned,
             revealed,
-            pos,
         }
     }
 
     // Setters
+    // Adds a mine, tiles may hold several
     pub fn set_mine(&mut self) {
-        self.mine = true;
+        self.mines += 1;
     }
 
     pub fn set_num(&mut self, num: i32) {
         self.num = num;
     }
 
+    pub fn set_split(&mut self, split: i32) {
+        self.split = split;
+    }
+
     pub fn set_flagged(&mut self, flagged: bool) {
-        self.flagged = flagged;
+        self.flags = flagged as i32;
+    }
+
+    pub fn set_flags(&mut self, flags: i32) {
+        self.flags = flags;
+    }
+
+    pub fn set_questioned(&mut self, questioned: bool) {
+        self.questioned = questioned;
     }
 
     pub fn reveal(&mut self) {
         if !self.revealed {
             self.revealed = true;
+            self.questioned = false;
         }
     }
 
-    pub fn set_pos(&mut self, pos: [i32; 2]) {
-        self.pos = pos;
-    }
-
     // Getters
     pub fn has_mine(&self) -> bool {
-        self.mine
+        self.mines > 0
+    }
+
+    pub fn get_mines(&self) -> i32 {
+        self.mines
     }
 
     pub fn get_num(&self) -> i32 {
         self.num
     }
 
+    // Under ClueRule::ColourSplit, the part of get_num() found in the same row or column
+    pub fn get_split(&self) -> i32 {
+        self.split
+    }
+
     pub fn is_flagged(&self) -> bool {
-        self.flagged
+        self.flags > 0
+    }
+
+    pub fn get_flags(&self) -> i32 {
+        self.flags
+    }
+
+    pub fn is_questioned(&self) -> bool {
+        self.questioned
     }
 
     pub fn is_revealed(&self) -> bool {
         self.revealed
     }
+}
 
-    pub fn get_pos(&self) -> [i32; 2] {
-        self.pos
-    }
+// The tiles of a board stored flat, row after row, so (x, y) is tile y * width + x.
+// Whether a tile has a mine, flag or question mark or is revealed takes one bit each,
+// and its numbers a byte each. The few tiles holding more than one mine or flag keep
+// their counts on the side
+#[derive(Clone, PartialEq)]
+pub struct MinesweeperBoard {
+    width: i32,
+    height: i32,
+    mines: BitSet,
+    flagged: BitSet,
+    questioned: BitSet,
+    revealed: BitSet,
+    nums: Vec<u8>,
+    splits: Vec<u8>,
+    extra_mines: HashMap<usize, i32>,
+    extra_flags: HashMap<usize, i32>,
 }
 
-pub fn build_minesweeper_board(height: i32, width: i32, mut mine_count: i32) -> MinesweeperBoard {
-    let mut board: MinesweeperBoard = vec![vec![Tile::empty(); width as usize]; height as usize];
-    let mut clean_tiles = height * width;
+impl MinesweeperBoard {
+    // An empty board with every tile hidden
+    pub fn new(width: i32, height: i32) -> Self {
+        let size = (width.max(0) * height.max(0)) as usize;
+        MinesweeperBoard {
+            width: width.max(0),
+            height: height.max(0),
+            mines: BitSet::new(size),
+            flagged: BitSet::new(size),
+            questioned: BitSet::new(size),
+            revealed: BitSet::new(size),
+            nums: vec![0; size],
+            splits: vec![0; size],
+            extra_mines: HashMap::new(),
+            extra_flags: HashMap::new(),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    pub fn tile(&self, x: i32, y: i32) -> Tile {
+        Tile::new(
+            self.get_mines(x, y),
+            self.get_num(x, y),
+            self.get_split(x, y),
+            self.get_flags(x, y),
+            self.is_questioned(x, y),
+            self.is_revealed(x, y),
+        )
+    }
+
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: Tile) {
+        let n = self.index(x, y);
+        self.set_mines(x, y, tile.get_mines());
+        self.set_num(x, y, tile.get_num());
+        self.set_split(x, y, tile.get_split());
+        self.set_flags(x, y, tile.get_flags());
+        self.questioned.set(n, tile.is_questioned());
+        self.revealed.set(n, tile.is_revealed());
+    }
 
-    for i in 0..board.len() {
-        for j in 0..board[i].len() {
-            board[i][j].set_pos([i as i32, j as i32])
+    // Every tile in row order
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| self.tile(x, y)))
+    }
+
+    // Setters
+    // Adds a mine, tiles may hold several
+    pub fn set_mine(&mut self, x: i32, y: i32) {
+        let mines = self.get_mines(x, y);
+        self.set_mines(x, y, mines + 1);
+    }
+
+    pub fn set_mines(&mut self, x: i32, y: i32, mines: i32) {
+        let n = self.index(x, y);
+        self.mines.set(n, mines > 0);
+        if mines > 1 {
+            self.extra_mines.insert(n, mines);
+        } else {
+            self.extra_mines.remove(&n);
         }
     }
 
-    while (mine_count > 0) && (clean_tiles > 0) {
-        let mut x = rand::thread_rng().gen_range(0..width);
-        let mut y = rand::thread_rng().gen_range(0..height);
+    // Numbers past 255 only come from boards with hundreds of mines per tile, and
+    // are capped there
+    pub fn set_num(&mut self, x: i32, y: i32, num: i32) {
+        let n = self.index(x, y);
+        self.nums[n] = num.clamp(0, u8::MAX as i32) as u8;
+    }
+
+    pub fn set_split(&mut self, x: i32, y: i32, split: i32) {
+        let n = self.index(x, y);
+        self.splits[n] = split.clamp(0, u8::MAX as i32) as u8;
+    }
+
+    pub fn set_flagged(&mut self, x: i32, y: i32, flagged: bool) {
+        self.set_flags(x, y, flagged as i32);
+    }
 
-        while board[y as usize][x as usize].has_mine() {
-            x = rand::thread_rng().gen_range(0..width);
-            y = rand::thread_rng().gen_range(0..height);
+    pub fn set_flags(&mut self, x: i32, y: i32, flags: i32) {
+        let n = self.index(x, y);
+        self.flagged.set(n, flags > 0);
+        if flags > 1 {
+            self.extra_flags.insert(n, flags);
+        } else {
+            self.extra_flags.remove(&n);
         }
+    }
 
-        board[y as usize][x as usize].set_mine();
-        mine_count -= 1;
-        clean_tiles -= 1;
+    // Takes every flag off the board
+    pub fn clear_flags(&mut self) {
+        self.flagged.clear();
+        self.extra_flags.clear();
     }
 
-    determine_tile_number(&mut board);
+    pub fn set_questioned(&mut self, x: i32, y: i32, questioned: bool) {
+        let n = self.index(x, y);
+        self.questioned.set(n, questioned);
+    }
 
-    board
-}
+    pub fn reveal(&mut self, x: i32, y: i32) {
+        let n = self.index(x, y);
+        self.revealed.set(n, true);
+        self.questioned.set(n, false);
+    }
 
-pub fn determine_tile_number(board: &mut MinesweeperBoard) {
-    let height: i32 = board.len() as i32;
+    // Getters
+    pub fn has_mine(&self, x: i32, y: i32) -> bool {
+        self.mines.get(self.index(x, y))
+    }
 
-    for j in 0..height {
-        let width: i32 = board[j as usize].len() as i32;
-        for i in 0..width {
-            let mut number = 0;
+    pub fn get_mines(&self, x: i32, y: i32) -> i32 {
+        let n = self.index(x, y);
+        match self.extra_mines.get(&n) {
+            Some(&mines) => mines,
+            None => self.mines.get(n) as i32,
+        }
+    }
 
-            if !board[j as usize][i as usize].has_mine() {
-                if j + 1 < height {
-                    if i + 1 < width && board[(j + 1) as usize][(i + 1) as usize].has_mine() {
-                        number += 1;
-                    }
-                    if i - 1 >= 0 && board[(j + 1) as usize][(i - 1) as usize].has_mine() {
-                        number += 1;
-                    }
-                    if board[(j + 1) as usize][i as usize].has_mine() {
-                        number += 1;
-                    }
-                }
-                if j - 1 >= 0 {
-                    if i + 1 < width && board[(j - 1) as usize][(i + 1) as usize].has_mine() {
-                        number += 1;
-                    }
-                    if i - 1 >= 0 && board[(j - 1) as usize][(i - 1) as usize].has_mine() {
-                        number += 1;
-                    }
-                    if board[(j - 1) as usize][i as usize].has_mine() {
-                        number += 1;
-                    }
-                }
-                if i + 1 < width && board[j as usize][(i + 1) as usize].has_mine() {
-                    number += 1;
-                }
-                if i - 1 >= 0 && board[j as usize][(i - 1) as usize].has_mine() {
-                    number += 1;
-                }
-            }
+    pub fn get_num(&self, x: i32, y: i32) -> i32 {
+        self.nums[self.index(x, y)] as i32
+    }
+
+    pub fn get_split(&self, x: i32, y: i32) -> i32 {
+        self.splits[self.index(x, y)] as i32
+    }
 
-            board[j as usize][i as usize].set_num(number)
+    pub fn is_flagged(&self, x: i32, y: i32) -> bool {
+        self.flagged.get(self.index(x, y))
+    }
+
+    pub fn get_flags(&self, x: i32, y: i32) -> i32 {
+        let n = self.index(x, y);
+        match self.extra_flags.get(&n) {
+            Some(&flags) => flags,
+            None => self.flagged.get(n) as i32,
         }
     }
+
+    pub fn is_questioned(&self, x: i32, y: i32) -> bool {
+        self.questioned.get(self.index(x, y))
+    }
+
+    pub fn is_revealed(&self, x: i32, y: i32) -> bool {
+        self.revealed.get(self.index(x, y))
+    }
+}
+
+// What the number on a revealed tile tells the player
+#[derive(Copy, Clone, PartialEq)]
+pub enum ClueRule {
+    // The number of mines around the tile
+    Standard,
+    // The number of mines around the tile, off by exactly one. Lies never show 0,
+    // so nothing cascades, and chording is disabled since flags can't match
+    Liar,
+    // The number of mines around the tile, with the part of it in the same row or
+    // column as the tile shown separately from the rest
+    ColourSplit,
 }
 
-pub fn cord_tile(board: &mut MinesweeperBoard, x: i32, y: i32) -> Result<bool, [i32; 2]> {
-    if board[y as usize][x as usize].get_num() == 0 {
-        return Err([-1, -1]);
+impl ClueRule {
+    pub fn allows_chording(&self) -> bool {
+        *self != ClueRule::Liar
     }
+}
+
+// Why a board couldn't be built
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenerationError {
+    NegativeMineCount(i32),
+    // More mines than every tile filled to max_per_tile would hold
+    TooManyMines { requested: i64, capacity: i64 },
+    // The placement rules ruled out every tile before all the mines were placed
+    NoRoom { placed: i64, requested: i64 },
+    // The placement rules made the chances of a mine too large to pick between
+    ChanceOverflow,
+}
 
-    let mut num_flagged = 0;
-    if y - 1 >= 0 {
-        if x - 1 >= 0 {
-            if board[(y - 1) as usize][(x - 1) as usize].is_flagged() {
-                num_flagged += 1;
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenerationError::NegativeMineCount(count) => {
+                write!(f, "{} is not a mine count", count)
             }
-        }
-        if x + 1 < board[(y - 1) as usize].len() as i32 {
-            if board[(y - 1) as usize][(x + 1) as usize].is_flagged() {
-                num_flagged += 1;
+            GenerationError::TooManyMines {
+                requested,
+                capacity,
+            } => write!(
+                f,
+                "{} mines were asked for but the board holds at most {}",
+                requested, capacity
+            ),
+            GenerationError::NoRoom { placed, requested } => write!(
+                f,
+                "the placement rules left room for only {} of {} mines",
+                placed, requested
+            ),
+            GenerationError::ChanceOverflow => {
+                write!(f, "the placement rules made the chances of a mine overflow")
             }
         }
-        if board[(y - 1) as usize][x as usize].is_flagged() {
-            num_flagged += 1;
-        }
     }
-    if y + 1 < board.len() as i32 {
-        if x - 1 >= 0 {
-            if board[(y + 1) as usize][(x - 1) as usize].is_flagged() {
-                num_flagged += 1;
-            }
-        }
-        if x + 1 < board[(y + 1) as usize].len() as i32 {
-            if board[(y + 1) as usize][(x + 1) as usize].is_flagged() {
-                num_flagged += 1;
-            }
-        }
-        if board[(y + 1) as usize][x as usize].is_flagged() {
-            num_flagged += 1;
-        }
+}
+
+impl std::error::Error for GenerationError {}
+
+// Places mine_count mines with at most max_per_tile on any one tile, spread as
+// placement asks. The same rng state always gives the same board
+pub fn build_minesweeper_board(
+    topology: &dyn Topology,
+    mine_count: i32,
+    max_per_tile: i32,
+    clue_rule: ClueRule,
+    placement: &Placement,
+    rng: &mut impl Rng,
+) -> Result<MinesweeperBoard, GenerationError> {
+    let [width, height] = topology.dimensions();
+    let mut board = MinesweeperBoard::new(width, height);
+    let positions = topology.positions();
+    let capacity = positions.len() as i64 * max_per_tile.max(0) as i64;
+
+    if mine_count < 0 {
+        return Err(GenerationError::NegativeMineCount(mine_count));
     }
-    if x - 1 >= 0 {
-        if board[y as usize][(x - 1) as usize].is_flagged() {
-            num_flagged += 1;
-        }
+    if mine_count as i64 > capacity {
+        return Err(GenerationError::TooManyMines {
+            requested: mine_count as i64,
+            capacity,
+        });
     }
-    if x + 1 < board[y as usize].len() as i32 {
-        if board[y as usize][(x + 1) as usize].is_flagged() {
-            num_flagged += 1;
+
+    if placement.is_uniform() {
+        // Each tile has max_per_tile slots for a mine. Picking distinct slots takes
+        // time in proportion to the mine count, however full the board gets
+        for slot in rand::seq::index::sample(rng, capacity as usize, mine_count as usize) {
+            let [x, y] = positions[slot / max_per_tile as usize];
+            board.set_mine(x, y);
+        }
+    } else {
+        let placed = place_mines(
+            &mut board,
+            topology,
+            &positions,
+            mine_count,
+            max_per_tile,
+            placement,
+            rng,
+        )?;
+        if placed < mine_count {
+            return Err(GenerationError::NoRoom {
+                placed: placed as i64,
+                requested: mine_count as i64,
+            });
         }
     }
 
-    let mut result: i32;
-    if num_flagged == board[y as usize][x as usize].get_num() {
-        if y - 1 >= 0 {
-            if x - 1 >= 0 {
-                result = reveal_tile(board, x - 1, y - 1);
-                if result == 1 {
-                    return Err([x - 1, y - 1]);
-                }
+    determine_tile_number(&mut board, topology, clue_rule, max_per_tile, rng);
+
+    Ok(board)
+}
+
+// Places mines one at a time, each tile's chance set by placement and the mines placed
+// so far, and returns how many fit before every tile was ruled out, or an error once
+// the chances grow past what an f64 holds. Placing a mine
+// only changes the chances of the tiles around it, so each costs about as much as
+// its neighbourhood rather than the whole board
+fn place_mines(
+    board: &mut MinesweeperBoard,
+    topology: &dyn Topology,
+    positions: &[[i32; 2]],
+    mine_count: i32,
+    max_per_tile: i32,
+    placement: &Placement,
+    rng: &mut impl Rng,
+) -> Result<i32, GenerationError> {
+    let [width, height] = topology.dimensions();
+    let mut index = vec![vec![usize::MAX; width as usize]; height as usize];
+    for (n, &[x, y]) in positions.iter().enumerate() {
+        index[y as usize][x as usize] = n;
+    }
+
+    let weights: Vec<f64> = positions
+        .iter()
+        .map(|&[x, y]| placement.weight(topology, x, y))
+        .collect();
+    // Neighbouring tiles holding a mine, and whether a tile is closer than
+    // min_spacing to one
+    let mut nearby = vec![0; positions.len()];
+    let mut crowded = vec![false; positions.len()];
+    let mut chances = ChanceTree::new(&weights);
+
+    for placed in 0..mine_count {
+        if !chances.total().is_finite() {
+            return Err(GenerationError::ChanceOverflow);
+        }
+        let n = match chances.pick(rng) {
+            Some(n) => n,
+            None => return Ok(placed),
+        };
+        let [x, y] = positions[n];
+        let first_mine = !board.has_mine(x, y);
+        board.set_mine(x, y);
+
+        let mut changed = vec![n];
+        if first_mine {
+            for [nx, ny] in topology.neighbours(x, y) {
+                let neighbour = index[ny as usize][nx as usize];
+                nearby[neighbour] += 1;
+                changed.push(neighbour);
             }
-            if x + 1 < board[(y - 1) as usize].len() as i32 {
-                result = reveal_tile(board, x + 1, y - 1);
-                if result == 1 {
-                    return Err([x + 1, y - 1]);
+        }
+
+        // Walk min_spacing - 1 steps out from the new mine
+        if placement.min_spacing > 1 {
+            let mut seen = vec![n];
+            let mut ring = vec![[x, y]];
+            for _ in 1..placement.min_spacing {
+                let mut next = Vec::new();
+                for [rx, ry] in ring {
+                    for [nx, ny] in topology.neighbours(rx, ry) {
+                        let neighbour = index[ny as usize][nx as usize];
+                        if !seen.contains(&neighbour) {
+                            seen.push(neighbour);
+                            next.push([nx, ny]);
+                        }
+                    }
                 }
+                ring = next;
             }
-            result = reveal_tile(board, x, y - 1);
-            if result == 1 {
-                return Err([x, y - 1]);
+            for &s in seen.iter() {
+                crowded[s] = true;
             }
+            changed.extend(seen);
         }
-        if y + 1 < board.len() as i32 {
-            if x - 1 >= 0 {
-                result = reveal_tile(board, x - 1, y + 1);
-                if result == 1 {
-                    return Err([x - 1, y + 1]);
-                }
-            }
-            if x + 1 < board[(y + 1) as usize].len() as i32 {
-                result = reveal_tile(board, x + 1, y + 1);
-                if result == 1 {
-                    return Err([x + 1, y + 1]);
+
+        for c in changed {
+            let [cx, cy] = positions[c];
+            let chance = if crowded[c] || board.get_mines(cx, cy) >= max_per_tile {
+                0.0
+            } else {
+                weights[c] * placement.cluster_factor(nearby[c])
+            };
+            chances.set(c, chance);
+        }
+    }
+
+    Ok(mine_count)
+}
+
+pub fn determine_tile_number(
+    board: &mut MinesweeperBoard,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+    rng: &mut impl Rng,
+) {
+    for [i, j] in topology.positions() {
+        let mut number = 0;
+        let mut split = 0;
+
+        if !board.has_mine(i, j) {
+            let neighbours = topology.neighbours(i, j);
+            for &[nx, ny] in neighbours.iter() {
+                let mines = board.get_mines(nx, ny);
+                number += mines;
+                if nx == i || ny == j {
+                    split += mines;
                 }
             }
-            result = reveal_tile(board, x, y + 1);
-            if result == 1 {
-                return Err([x, y + 1]);
+
+            if clue_rule == ClueRule::Liar {
+                let most = neighbours.len() as i32 * max_per_tile;
+                number = if number <= 1 {
+                    number + 1
+                } else if number == most || rng.gen_bool(0.5) {
+                    number - 1
+                } else {
+                    number + 1
+                };
             }
         }
-        if x - 1 >= 0 {
-            result = reveal_tile(board, x - 1, y);
-            if result == 1 {
-                return Err([x - 1, y]);
-            }
+
+        board.set_num(i, j, number);
+        board.set_split(
+            i,
+            j,
+            if clue_rule == ClueRule::ColourSplit {
+                split
+            } else {
+                0
+            },
+        );
+    }
+}
+
+// Reveals every unflagged neighbour of a number with that many flags around it,
+// returning the tiles opened. Err holds the first mine hit, with any tiles opened
+// before it left open
+pub fn cord_tile(
+    board: &mut MinesweeperBoard,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    x: i32,
+    y: i32,
+) -> Result<Vec<[i32; 2]>, [i32; 2]> {
+    if board.get_num(x, y) == 0 || !clue_rule.allows_chording() {
+        return Ok(Vec::new());
+    }
+
+    let neighbours = topology.neighbours(x, y);
+    let num_flagged: i32 = neighbours
+        .iter()
+        .map(|[nx, ny]| board.get_flags(*nx, *ny))
+        .sum();
+
+    if num_flagged != board.get_num(x, y) {
+        return Ok(Vec::new());
+    }
+
+    let mut opened = Vec::new();
+    for [nx, ny] in neighbours {
+        opened.append(&mut reveal_tile(board, topology, nx, ny)?);
+    }
+
+    Ok(opened)
+}
+
+// Reveals (x, y) and, from each 0 it opens, every neighbour in turn. Flags stop the
+// cascade and numbers bound it. Returns the tiles opened, nearest first, which is
+// empty for tiles off the board, already revealed or flagged. Err holds the position
+// of a clicked mine
+pub fn reveal_tile(
+    board: &mut MinesweeperBoard,
+    topology: &dyn Topology,
+    x: i32,
+    y: i32,
+) -> Result<Vec<[i32; 2]>, [i32; 2]> {
+    if !topology.contains(x, y) || board.is_revealed(x, y) || board.is_flagged(x, y) {
+        return Ok(Vec::new());
+    }
+
+    if board.has_mine(x, y) {
+        return Err([x, y]);
+    }
+
+    // A queue rather than recursion, so huge empty areas can't overflow the stack
+    board.reveal(x, y);
+    let mut opened = vec![[x, y]];
+    let mut next = 0;
+    while next < opened.len() {
+        let [ox, oy] = opened[next];
+        next += 1;
+        if board.get_num(ox, oy) != 0 {
+            continue;
         }
-        if x + 1 < board[y as usize].len() as i32 {
-            result = reveal_tile(board, x + 1, y);
-            if result == 1 {
-                return Err([x + 1, y]);
+
+        for [nx, ny] in topology.neighbours(ox, oy) {
+            if !board.is_revealed(nx, ny) && !board.is_flagged(nx, ny) && !board.has_mine(nx, ny) {
+                board.reveal(nx, ny);
+                opened.push([nx, ny]);
             }
         }
+    }
+
+    Ok(opened)
+}
+
+// Cycles a hidden tile through none -> 1 flag -> ... -> max_flags flags -> question
+// -> none, skipping the question mark when question_marks is off. Only flags change
+// the mine counter
+pub fn flag_tile(
+    board: &mut MinesweeperBoard,
+    x: i32,
+    y: i32,
+    mut mine_count: i32,
+    question_marks: bool,
+    max_flags: i32,
+) -> i32 {
+    if board.is_revealed(x, y) {
+        return mine_count;
+    }
+
+    let flags = board.get_flags(x, y);
+    if flags >= max_flags {
+        mine_count += flags;
+        board.set_flags(x, y, 0);
+        board.set_questioned(x, y, question_marks);
+    } else if flags > 0 {
+        board.set_flags(x, y, flags + 1);
+        mine_count -= 1;
+    } else if board.is_questioned(x, y) {
+        board.set_questioned(x, y, false);
     } else {
-        return Ok(false);
+        board.set_flagged(x, y, true);
+        mine_count -= 1;
+    }
+    mine_count
+}
+
+// The fewest clicks that clear the board without flags or chords: one per opening of
+// connected zeros, which also reveals the numbers around it, plus one per other
+// safe tile
+pub fn three_bv(board: &MinesweeperBoard, topology: &dyn Topology) -> i32 {
+    let [width, height] = topology.dimensions();
+    let mut counted = vec![vec![false; width as usize]; height as usize];
+    let mut clicks = 0;
+
+    for [x, y] in topology.positions() {
+        let tile = board.tile(x, y);
+        if tile.has_mine() || tile.get_num() != 0 || counted[y as usize][x as usize] {
+            continue;
+        }
+
+        clicks += 1;
+        counted[y as usize][x as usize] = true;
+        let mut opening = vec![[x, y]];
+        while let Some([ox, oy]) = opening.pop() {
+            for [nx, ny] in topology.neighbours(ox, oy) {
+                let neighbour = board.tile(nx, ny);
+                if !counted[ny as usize][nx as usize] && !neighbour.has_mine() {
+                    counted[ny as usize][nx as usize] = true;
+                    if neighbour.get_num() == 0 {
+                        opening.push([nx, ny]);
+                    }
+                }
+            }
+        }
+    }
+
+    for [x, y] in topology.positions() {
+        if !board.has_mine(x, y) && !counted[y as usize][x as usize] {
+            clicks += 1;
+        }
     }
 
-    return Ok(true);
+    clicks
 }
 
-pub fn reveal_tile(board: &mut MinesweeperBoard, x: i32, y: i32) -> i32 {
-    if y < 0
-        || y >= board.len() as i32
-        || x < 0
-        || x >= board[y as usize].len() as i32
-        || board[y as usize][x as usize].is_revealed()
-        || board[y as usize][x as usize].is_flagged()
-    {
-        return 2;
+pub fn revealed_safe_tiles(board: &MinesweeperBoard) -> i32 {
+    board.revealed.count_without(&board.mines) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::topology::SquareGrid;
+
+    fn build(
+        size: i32,
+        mine_count: i32,
+        max_per_tile: i32,
+        placement: &Placement,
+    ) -> Result<MinesweeperBoard, GenerationError> {
+        build_minesweeper_board(
+            &SquareGrid::new(size, size),
+            mine_count,
+            max_per_tile,
+            ClueRule::Standard,
+            placement,
+            &mut StdRng::seed_from_u64(7),
+        )
     }
 
-    if board[y as usize][x as usize].has_mine() {
-        return 1;
+    fn mines(board: &MinesweeperBoard) -> Vec<[i32; 2]> {
+        SquareGrid::new(board.width(), board.height())
+            .positions()
+            .into_iter()
+            .filter(|[x, y]| board.has_mine(*x, *y))
+            .collect()
     }
 
-    let mut tile = board[y as usize][x as usize];
-    tile.reveal();
-    board[y as usize][x as usize] = tile;
+    #[test]
+    fn negative_mine_count() {
+        let error = build(3, -1, 1, &Placement::default()).err();
+        assert_eq!(error, Some(GenerationError::NegativeMineCount(-1)));
+    }
 
-    if tile.get_num() == 0 {
-        // Top Left
-        reveal_tile(board, x - 1, y - 1);
-        // Top
-        reveal_tile(board, x, y - 1);
-        // Top Right
-        reveal_tile(board, x + 1, y - 1);
+    #[test]
+    fn too_many_mines() {
+        let error = build(3, 10, 1, &Placement::default()).err();
+        assert_eq!(
+            error,
+            Some(GenerationError::TooManyMines {
+                requested: 10,
+                capacity: 9
+            })
+        );
+    }
 
-        // Left
-        reveal_tile(board, x - 1, y);
-        // Right
-        reveal_tile(board, x + 1, y);
+    #[test]
+    fn full_board_with_several_mines_per_tile() {
+        let board = build(3, 18, 2, &Placement::default()).unwrap();
+        let total: i32 = board.tiles().map(|t| t.get_mines()).sum();
+        assert_eq!(total, 18);
+        assert!(board.tiles().all(|t| t.get_mines() == 2));
+    }
 
-        // Bottom Left
-        reveal_tile(board, x - 1, y + 1);
-        // Bottom
-        reveal_tile(board, x, y + 1);
-        // Bottom Right
-        reveal_tile(board, x + 1, y + 1);
+    #[test]
+    fn no_room_left_by_spacing() {
+        // Any mine on a 3x3 board is within two steps of every tile
+        let placement = Placement {
+            min_spacing: 3,
+            ..Placement::default()
+        };
+        let error = build(3, 2, 1, &placement).err();
+        assert_eq!(
+            error,
+            Some(GenerationError::NoRoom {
+                placed: 1,
+                requested: 2
+            })
+        );
     }
 
-    return 0;
-}
+    #[test]
+    fn exact_count_with_spacing() {
+        let placement = Placement {
+            min_spacing: 2,
+            ..Placement::default()
+        };
+        for seed in 0..20 {
+            let board = build_minesweeper_board(
+                &SquareGrid::new(9, 9),
+                10,
+                1,
+                ClueRule::Standard,
+                &placement,
+                &mut StdRng::seed_from_u64(seed),
+            )
+            .unwrap();
+            let mines = mines(&board);
+            assert_eq!(mines.len(), 10);
+            for [x, y] in mines.iter() {
+                for [ox, oy] in mines.iter() {
+                    let touching = (x - ox).abs() <= 1 && (y - oy).abs() <= 1;
+                    assert!(!touching || [x, y] == [ox, oy]);
+                }
+            }
+        }
+    }
 
-pub fn flag_tile(board: &mut MinesweeperBoard, x: i32, y: i32, mut mine_count: i32) -> i32 {
-    let mut selected_tile = board[y as usize][x as usize];
+    #[test]
+    fn exact_count_inside_safe_border() {
+        let placement = Placement {
+            safe_border: 2,
+            ..Placement::default()
+        };
+        let board = build(9, 25, 1, &placement).unwrap();
+        let mines = mines(&board);
+        // The 5x5 middle is the only room, and it is filled
+        assert_eq!(mines.len(), 25);
+        assert!(mines
+            .iter()
+            .all(|[x, y]| (2..7).contains(x) && (2..7).contains(y)));
+        assert!(build(9, 26, 1, &placement).is_err());
+    }
 
-    if !(selected_tile.is_revealed()) {
-        if selected_tile.is_flagged() {
-            selected_tile.set_flagged(false);
-            mine_count += 1
-        } else {
-            selected_tile.set_flagged(true);
-            mine_count -= 1;
+    #[test]
+    fn huge_empty_board_on_a_small_stack() {
+        let opened = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| {
+                let mut board = MinesweeperBoard::new(2000, 2000);
+                let topology = SquareGrid::new(2000, 2000);
+                reveal_tile(&mut board, &topology, 1000, 1000).map(|o| o.len())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(opened, Ok(4_000_000));
+    }
+
+    #[test]
+    fn flags_stop_the_cascade() {
+        let topology = SquareGrid::new(5, 5);
+        let mut board = MinesweeperBoard::new(5, 5);
+        for y in 0..5 {
+            board.set_flagged(2, y, true);
+        }
+        let opened = reveal_tile(&mut board, &topology, 0, 0).unwrap();
+        assert_eq!(opened.len(), 10);
+        assert!(opened.iter().all(|[x, _]| *x < 2));
+        assert!((0..5).all(|y| !board.is_revealed(2, y) && !board.is_revealed(3, y)));
+        assert!(reveal_tile(&mut board, &topology, 2, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn numbers_bound_the_cascade() {
+        let topology = SquareGrid::new(5, 5);
+        let mut board = MinesweeperBoard::new(5, 5);
+        for y in 0..5 {
+            board.set_mine(2, y);
         }
+        let mut rng = StdRng::seed_from_u64(1);
+        determine_tile_number(&mut board, &topology, ClueRule::Standard, 1, &mut rng);
+
+        let opened = reveal_tile(&mut board, &topology, 0, 2).unwrap();
+        assert_eq!(opened.len(), 10);
+        assert!(opened.iter().all(|[x, _]| *x < 2));
+        assert!((0..5).all(|y| board.get_num(1, y) > 0 && board.is_revealed(1, y)));
+        assert_eq!(reveal_tile(&mut board, &topology, 2, 2), Err([2, 2]));
+    }
+
+    #[test]
+    fn opened_nearest_first() {
+        let topology = SquareGrid::new(9, 9);
+        let mut board = MinesweeperBoard::new(9, 9);
+        let opened = reveal_tile(&mut board, &topology, 3, 5).unwrap();
+        assert_eq!(opened.len(), 81);
+        assert_eq!(opened[0], [3, 5]);
+        let steps: Vec<i32> = opened
+            .iter()
+            .map(|[x, y]| (x - 3).abs().max((y - 5).abs()))
+            .collect();
+        assert!(steps.windows(2).all(|w| w[0] <= w[1]));
     }
 
-    board[y as usize][x as usize] = selected_tile;
-    return mine_count;
+    #[test]
+    fn flags_cycle_through_question_mark() {
+        let mut board = MinesweeperBoard::new(2, 1);
+        let mut mine_count = 5;
+        let mut marks = Vec::new();
+        for _ in 0..4 {
+            mine_count = flag_tile(&mut board, 0, 0, mine_count, true, 2);
+            marks.push((board.get_flags(0, 0), board.is_questioned(0, 0), mine_count));
+        }
+        assert_eq!(
+            marks,
+            vec![(1, false, 4), (2, false, 3), (0, true, 5), (0, false, 5)]
+        );
+
+        // Without question marks two flags go straight back to none, and revealed
+        // tiles can't be flagged
+        flag_tile(&mut board, 0, 0, 5, false, 2);
+        flag_tile(&mut board, 0, 0, 4, false, 2);
+        assert_eq!(flag_tile(&mut board, 0, 0, 3, false, 2), 5);
+        assert!(!board.is_questioned(0, 0) && !board.is_flagged(0, 0));
+        board.reveal(1, 0);
+        assert_eq!(flag_tile(&mut board, 1, 0, 5, true, 2), 5);
+        assert!(!board.is_flagged(1, 0));
+    }
 }
@@ -0,0 +1,615 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::minesweeper;
+use crate::minesweeper::{ClueRule, MinesweeperBoard};
+use crate::placement::Placement;
+use crate::topology::SquareGrid;
+use crate::visible::{VisibleBoard, VisibleTile};
+
+// How long a write to a client may block before the client is dropped. Broadcasts hold
+// the game lock, so a client that stops reading would otherwise stall every move
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// What happens to the team when somebody reveals a mine
+#[derive(Copy, Clone, PartialEq)]
+pub enum MinePenalty {
+    // The mine is marked and the team loses one of its shared lives
+    LoseLife(i32),
+    // The game is over for everyone
+    EndGame,
+}
+
+#[derive(Copy, Clone)]
+pub struct Player {
+    pub id: usize,
+    pub cursor: [i32; 2],
+    pub revealed: i32,
+    pub correct_flags: i32,
+    pub connected: bool,
+}
+
+// Everything a client needs to draw the shared game. Clients only get the board as
+// the players see it, so the mines stay on the server until the game ends
+pub struct Snapshot {
+    pub board: VisibleBoard,
+    pub game_result: i32,
+    pub mine_count: i32,
+    // Only set when the team plays with lives
+    pub lives: Option<i32>,
+    pub hit_mine: [i32; 2],
+    pub players: Vec<Player>,
+}
+
+struct SharedGame {
+    board: MinesweeperBoard,
+    height: i32,
+    width: i32,
+    topology: SquareGrid,
+    start_mine_count: i32,
+    mine_count: i32,
+    penalty: MinePenalty,
+    lives: i32,
+    game_result: i32,
+    hit_mine: [i32; 2],
+    flagged_by: Vec<Vec<Option<usize>>>,
+    players: Vec<Player>,
+    streams: Vec<Option<TcpStream>>,
+}
+
+impl SharedGame {
+    fn new(height: i32, width: i32, mine_count: i32, penalty: MinePenalty) -> Self {
+        let mut game = SharedGame {
+            board: MinesweeperBoard::new(width, height),
+            height,
+            width,
+            topology: SquareGrid::new(width, height),
+            start_mine_count: mine_count,
+            mine_count,
+            penalty,
+            lives: 0,
+            game_result: 0,
+            hit_mine: [-1, -1],
+            flagged_by: Vec::new(),
+            players: Vec::new(),
+            streams: Vec::new(),
+        };
+        game.reset();
+        game
+    }
+
+    fn reset(&mut self) {
+        self.board = minesweeper::build_minesweeper_board(
+            &self.topology,
+            self.start_mine_count,
+            1,
+            ClueRule::Standard,
+            &Placement::default(),
+            &mut rand::thread_rng(),
+        )
+        .expect("host checks the mine count fits");
+        self.mine_count = self.start_mine_count;
+        self.lives = match self.penalty {
+            MinePenalty::LoseLife(lives) => lives,
+            MinePenalty::EndGame => 1,
+        };
+        self.game_result = 0;
+        self.hit_mine = [-1, -1];
+        self.flagged_by = vec![vec![None; self.width as usize]; self.height as usize];
+        for player in self.players.iter_mut() {
+            player.revealed = 0;
+            player.correct_flags = 0;
+        }
+    }
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    fn reveal(&mut self, id: usize, x: i32, y: i32) {
+        if self.game_result != 0 || !self.in_bounds(x, y) {
+            return;
+        }
+
+        // Actions are applied one at a time under the game lock, so when two players
+        // click the same tile the second click finds it revealed and does nothing
+        let before = minesweeper::revealed_safe_tiles(&self.board);
+        let mut hit = minesweeper::reveal_tile(&mut self.board, &self.topology, x, y).err();
+        if hit.is_none() && self.board.is_revealed(x, y) {
+            hit = minesweeper::cord_tile(&mut self.board, &self.topology, ClueRule::Standard, x, y)
+                .err();
+        }
+        self.players[id].revealed += minesweeper::revealed_safe_tiles(&self.board) - before;
+
+        if let Some(c) = hit {
+            self.hit_mine = c;
+            self.lives -= 1;
+            if self.lives <= 0 {
+                self.game_result = 1;
+            } else {
+                // Mark the mine so nobody steps on it again
+                self.board.set_flagged(c[0], c[1], true);
+                self.board.set_questioned(c[0], c[1], false);
+                self.flagged_by[c[1] as usize][c[0] as usize] = None;
+                self.mine_count -= 1;
+            }
+        }
+        self.check_win();
+    }
+
+    fn mark(&mut self, id: usize, x: i32, y: i32, flagged: bool, questioned: bool) {
+        if self.game_result != 0 || !self.in_bounds(x, y) {
+            return;
+        }
+
+        // A mark message carries the state the player wants, so two players flagging
+        // the same tile at once agree instead of toggling each other's flag away
+        let was_flagged = self.board.is_flagged(x, y);
+        if self.board.is_revealed(x, y)
+            || (was_flagged == flagged && self.board.is_questioned(x, y) == questioned)
+        {
+            return;
+        }
+        self.mine_count += was_flagged as i32 - flagged as i32;
+        self.board.set_flagged(x, y, flagged);
+        self.board.set_questioned(x, y, questioned && !flagged);
+        self.flagged_by[y as usize][x as usize] = if flagged { Some(id) } else { None };
+        self.count_flags();
+    }
+
+    fn count_flags(&mut self) {
+        for player in self.players.iter_mut() {
+            player.correct_flags = 0;
+        }
+        for j in 0..self.height {
+            for i in 0..self.width {
+                if let Some(id) = self.flagged_by[j as usize][i as usize] {
+                    if self.board.has_mine(i, j) {
+                        self.players[id].correct_flags += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_win(&mut self) {
+        let tiles_to_win = (self.height * self.width) - self.start_mine_count;
+        if self.game_result == 0 && minesweeper::revealed_safe_tiles(&self.board) == tiles_to_win {
+            for j in 0..self.height {
+                for i in 0..self.width {
+                    self.board.set_flagged(i, j, true);
+                }
+            }
+            self.game_result = 2;
+        }
+    }
+
+    fn state_message(&self) -> String {
+        let view = if self.game_result == 0 {
+            VisibleBoard::new(&self.board)
+        } else {
+            let hit = self.in_bounds(self.hit_mine[0], self.hit_mine[1]);
+            VisibleBoard::after_game(&self.board, hit.then_some(self.hit_mine))
+        };
+        let rows: Vec<String> = (0..self.height)
+            .map(|j| {
+                (0..self.width)
+                    .map(|i| encode_tile(view.tile(i, j)))
+                    .collect()
+            })
+            .collect();
+        let players: Vec<String> = self
+            .players
+            .iter()
+            .filter(|p| p.connected)
+            .map(|p| {
+                format!(
+                    "{},{},{},{},{}",
+                    p.id, p.cursor[0], p.cursor[1], p.revealed, p.correct_flags
+                )
+            })
+            .collect();
+
+        format!(
+            "STATE {} {} {} {} {} {} {}\n",
+            self.game_result,
+            self.mine_count,
+            match self.penalty {
+                MinePenalty::LoseLife(_) => self.lives.to_string(),
+                MinePenalty::EndGame => "-".to_string(),
+            },
+            self.hit_mine[0],
+            self.hit_mine[1],
+            rows.join("/"),
+            if players.is_empty() {
+                "-".to_string()
+            } else {
+                players.join(";")
+            }
+        )
+    }
+
+    fn broadcast(&mut self) {
+        let message = self.state_message();
+        for (id, slot) in self.streams.iter_mut().enumerate() {
+            if let Some(stream) = slot {
+                if stream.write_all(message.as_bytes()).is_err() {
+                    *slot = None;
+                    self.players[id].connected = false;
+                }
+            }
+        }
+    }
+}
+
+// Starts the authoritative game server in the background
+pub fn host(
+    port: u16,
+    height: i32,
+    width: i32,
+    mine_count: i32,
+    penalty: MinePenalty,
+) -> std::io::Result<()> {
+    if !(0..=height * width).contains(&mine_count) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Mine count doesn't fit on the board",
+        ));
+    }
+
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let game = Arc::new(Mutex::new(SharedGame::new(
+        height, width, mine_count, penalty,
+    )));
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let game = Arc::clone(&game);
+            thread::spawn(move || serve_player(game, stream));
+        }
+    });
+
+    Ok(())
+}
+
+fn serve_player(game: Arc<Mutex<SharedGame>>, mut stream: TcpStream) {
+    if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+        return;
+    }
+    let writer = match stream.try_clone() {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+
+    let id = {
+        let mut game = game.lock().unwrap();
+        let id = game.players.len();
+        // Greet the player under the lock and before their stream joins the broadcasts,
+        // so WELCOME is always the first line they get
+        let connected = stream
+            .write_all(format!("WELCOME {}\n", id).as_bytes())
+            .is_ok();
+        game.players.push(Player {
+            id,
+            cursor: [-1, -1],
+            revealed: 0,
+            correct_flags: 0,
+            connected,
+        });
+        game.streams.push(connected.then_some(writer));
+        if !connected {
+            return;
+        }
+        game.broadcast();
+        id
+    };
+
+    let reader = BufReader::new(stream);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let args: Vec<i32> = parts
+            .iter()
+            .skip(1)
+            .filter_map(|p| p.parse().ok())
+            .collect();
+
+        let mut game = game.lock().unwrap();
+        match (parts.first().copied(), args.as_slice()) {
+            (Some("CURSOR"), [x, y]) => game.players[id].cursor = [*x, *y],
+            (Some("REVEAL"), [x, y]) => game.reveal(id, *x, *y),
+            (Some("MARK"), [x, y, f, q]) => game.mark(id, *x, *y, *f != 0, *q != 0),
+            (Some("RESET"), []) => game.reset(),
+            _ => continue,
+        }
+        game.broadcast();
+    }
+
+    let mut game = game.lock().unwrap();
+    game.players[id].connected = false;
+    game.streams[id] = None;
+    game.broadcast();
+}
+
+fn encode_tile(tile: VisibleTile) -> char {
+    match tile {
+        VisibleTile::Revealed(num) => std::char::from_digit(num as u32, 10).unwrap_or('0'),
+        VisibleTile::Flagged(_) => 'F',
+        VisibleTile::Questioned => '?',
+        VisibleTile::Mine(_) => '*',
+        VisibleTile::Exploded => 'X',
+        VisibleTile::WrongFlag => 'W',
+        VisibleTile::Hidden => '.',
+    }
+}
+
+fn decode_tile(c: char) -> VisibleTile {
+    match c {
+        'F' => VisibleTile::Flagged(1),
+        '?' => VisibleTile::Questioned,
+        '*' => VisibleTile::Mine(1),
+        'X' => VisibleTile::Exploded,
+        'W' => VisibleTile::WrongFlag,
+        '.' => VisibleTile::Hidden,
+        _ => VisibleTile::Revealed(c.to_digit(10).unwrap_or(0) as i32),
+    }
+}
+
+fn parse_state(line: &str) -> Option<Snapshot> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 8 || parts[0] != "STATE" {
+        return None;
+    }
+
+    let rows: Vec<&str> = parts[6].split('/').collect();
+    let width = rows[0].chars().count() as i32;
+    let mut board = VisibleBoard::hidden(width, rows.len() as i32);
+    for (j, row) in rows.iter().enumerate() {
+        for (i, c) in row.chars().take(width as usize).enumerate() {
+            board.set_tile(i as i32, j as i32, decode_tile(c));
+        }
+    }
+
+    let mut players = Vec::new();
+    if parts[7] != "-" {
+        for entry in parts[7].split(';') {
+            let v: Vec<i32> = entry.split(',').filter_map(|p| p.parse().ok()).collect();
+            if v.len() == 5 {
+                players.push(Player {
+                    id: v[0] as usize,
+                    cursor: [v[1], v[2]],
+                    revealed: v[3],
+                    correct_flags: v[4],
+                    connected: true,
+                });
+            }
+        }
+    }
+
+    Some(Snapshot {
+        board,
+        game_result: parts[1].parse().ok()?,
+        mine_count: parts[2].parse().ok()?,
+        lives: parts[3].parse().ok(),
+        hit_mine: [parts[4].parse().ok()?, parts[5].parse().ok()?],
+        players,
+    })
+}
+
+// A connection to a game server, owned by the GUI
+pub struct Session {
+    id: usize,
+    stream: TcpStream,
+    snapshots: Receiver<Snapshot>,
+}
+
+impl Session {
+    // Connects and blocks until the first board arrives
+    pub fn connect(addr: &str) -> std::io::Result<(Session, Snapshot)> {
+        let stream = TcpStream::connect(addr)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let id = line
+            .strip_prefix("WELCOME ")
+            .and_then(|l| l.trim().parse().ok())
+            .ok_or_else(|| std::io::Error::other("unexpected greeting from server"))?;
+
+        let first = loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(std::io::Error::other("server closed the connection"));
+            }
+            if let Some(snapshot) = parse_state(&line) {
+                break snapshot;
+            }
+        };
+
+        let (sender, receiver): (Sender<Snapshot>, Receiver<Snapshot>) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                if let Some(snapshot) = parse_state(&line) {
+                    if sender.send(snapshot).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok((
+            Session {
+                id,
+                stream,
+                snapshots: receiver,
+            },
+            first,
+        ))
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    // Returns the newest snapshot received since the last poll
+    pub fn poll(&self) -> Option<Snapshot> {
+        self.snapshots.try_iter().last()
+    }
+
+    pub fn send_cursor(&mut self, x: i32, y: i32) {
+        self.send(format!("CURSOR {} {}\n", x, y));
+    }
+
+    pub fn send_reveal(&mut self, x: i32, y: i32) {
+        self.send(format!("REVEAL {} {}\n", x, y));
+    }
+
+    pub fn send_mark(&mut self, x: i32, y: i32, flagged: bool, questioned: bool) {
+        self.send(format!(
+            "MARK {} {} {} {}\n",
+            x, y, flagged as i32, questioned as i32
+        ));
+    }
+
+    pub fn send_reset(&mut self) {
+        self.send("RESET\n".to_string());
+    }
+
+    fn send(&mut self, message: String) {
+        // A lost connection shows up as the board no longer updating
+        let _ = self.stream.write_all(message.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    // A 3x3 board with its one mine in the bottom right corner, and two players
+    fn two_player_game() -> SharedGame {
+        let mut game = SharedGame::new(3, 3, 1, MinePenalty::LoseLife(2));
+        game.board = MinesweeperBoard::new(3, 3);
+        game.board.set_mine(2, 2);
+        minesweeper::determine_tile_number(
+            &mut game.board,
+            &game.topology,
+            ClueRule::Standard,
+            1,
+            &mut StdRng::seed_from_u64(0),
+        );
+        for id in 0..2 {
+            game.players.push(Player {
+                id,
+                cursor: [id as i32, 0],
+                revealed: 0,
+                correct_flags: 0,
+                connected: true,
+            });
+            game.streams.push(None);
+        }
+        game
+    }
+
+    #[test]
+    fn tiles_round_trip() {
+        let tiles = (0..=8).map(VisibleTile::Revealed).chain([
+            VisibleTile::Hidden,
+            VisibleTile::Flagged(1),
+            VisibleTile::Questioned,
+            VisibleTile::Mine(1),
+            VisibleTile::Exploded,
+            VisibleTile::WrongFlag,
+        ]);
+        for tile in tiles {
+            assert_eq!(decode_tile(encode_tile(tile)), tile);
+        }
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let mut game = two_player_game();
+        game.reveal(0, 1, 1);
+        game.mark(1, 2, 2, true, false);
+        game.players[1].cursor = [2, 2];
+
+        let snapshot = parse_state(&game.state_message()).unwrap();
+        assert!(snapshot.board == VisibleBoard::new(&game.board));
+        assert_eq!(snapshot.game_result, 0);
+        assert_eq!(snapshot.mine_count, 0);
+        assert_eq!(snapshot.lives, Some(2));
+        assert_eq!(snapshot.hit_mine, [-1, -1]);
+        let players: Vec<_> = snapshot
+            .players
+            .iter()
+            .map(|p| (p.id, p.cursor, p.revealed, p.correct_flags))
+            .collect();
+        assert_eq!(players, vec![(0, [0, 0], 1, 0), (1, [2, 2], 0, 1)]);
+    }
+
+    #[test]
+    fn hidden_mines_stay_on_the_server() {
+        let game = two_player_game();
+        let message = game.state_message();
+        assert!(!message.contains('*'));
+        assert_eq!(
+            parse_state(&message).unwrap().board.tile(2, 2),
+            VisibleTile::Hidden
+        );
+    }
+
+    #[test]
+    fn disconnected_players_are_left_out() {
+        let mut game = two_player_game();
+        game.players[0].connected = false;
+        let snapshot = parse_state(&game.state_message()).unwrap();
+        assert_eq!(snapshot.players.len(), 1);
+        assert_eq!(snapshot.players[0].id, 1);
+    }
+
+    #[test]
+    fn second_reveal_of_a_tile_does_nothing() {
+        let mut game = two_player_game();
+        game.reveal(0, 1, 1);
+        game.reveal(1, 1, 1);
+        assert_eq!(game.players[0].revealed, 1);
+        assert_eq!(game.players[1].revealed, 0);
+        assert_eq!(game.game_result, 0);
+    }
+
+    #[test]
+    fn flagging_the_same_tile_twice_agrees() {
+        let mut game = two_player_game();
+        game.mark(0, 2, 2, true, false);
+        game.mark(1, 2, 2, true, false);
+        assert!(game.board.is_flagged(2, 2));
+        assert_eq!(game.mine_count, 0);
+        // The flag stays credited to whoever placed it
+        assert_eq!(game.players[0].correct_flags, 1);
+        assert_eq!(game.players[1].correct_flags, 0);
+
+        game.mark(1, 2, 2, false, false);
+        game.mark(0, 2, 2, false, false);
+        assert!(!game.board.is_flagged(2, 2));
+        assert_eq!(game.mine_count, 1);
+        assert_eq!(game.players[0].correct_flags, 0);
+    }
+
+    #[test]
+    fn marks_on_revealed_tiles_are_ignored() {
+        let mut game = two_player_game();
+        game.reveal(0, 1, 1);
+        game.mark(1, 1, 1, true, false);
+        assert!(!game.board.is_flagged(1, 1));
+        assert_eq!(game.mine_count, 1);
+    }
+}
@@ -0,0 +1,19 @@
+// Command line options shared by the game and the simulate tool
+
+// The argument after flag, the first time it was given
+pub fn option_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    option_values(args, flag).first().copied()
+}
+
+// The argument after each time flag was given
+pub fn option_values<'a>(args: &'a [String], flag: &str) -> Vec<&'a str> {
+    args.iter()
+        .enumerate()
+        .filter(|(_, a)| *a == flag)
+        .map(|(n, _)| {
+            args.get(n + 1)
+                .map(|v| v.as_str())
+                .expect("Missing option value")
+        })
+        .collect()
+}
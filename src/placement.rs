@@ -0,0 +1,233 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::topology::Topology;
+
+// How much each neighbouring mine multiplies a tile's chance, raised to the
+// clustering power
+const CLUSTER_STRENGTH: f64 = 3.0;
+
+// How mines are spread over the board. The default places them uniformly
+#[derive(Clone)]
+pub struct Placement {
+    // Relative chance of a mine in each region, stretched over the board, or None for
+    // the same chance everywhere
+    pub density: Option<Vec<Vec<f64>>>,
+    // Above 0 pulls new mines next to placed ones, below 0 pushes them apart
+    pub clustering: f64,
+    // Steps between any two mines, 1 allowing mines to touch
+    pub min_spacing: i32,
+    // Width of the ring along the edges of the board kept free of mines
+    pub safe_border: i32,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement {
+            density: None,
+            clustering: 0.0,
+            min_spacing: 1,
+            safe_border: 0,
+        }
+    }
+}
+
+impl Placement {
+    pub fn is_uniform(&self) -> bool {
+        self.density.is_none()
+            && self.clustering == 0.0
+            && self.min_spacing <= 1
+            && self.safe_border <= 0
+    }
+
+    // Chance of a mine on (x, y) before any mines are placed, 0 where none may go
+    pub fn weight(&self, topology: &dyn Topology, x: i32, y: i32) -> f64 {
+        let [width, height] = topology.dimensions();
+        // Each layer of a 3D board has its own border
+        let layer_height = height / topology.layers();
+        let layer_y = y % layer_height;
+        if x < self.safe_border
+            || layer_y < self.safe_border
+            || x >= width - self.safe_border
+            || layer_y >= layer_height - self.safe_border
+        {
+            return 0.0;
+        }
+
+        match &self.density {
+            Some(density) => {
+                let row = &density[(layer_y * density.len() as i32 / layer_height) as usize];
+                row[(x * row.len() as i32 / width) as usize]
+            }
+            None => 1.0,
+        }
+    }
+
+    // How much a tile's chance grows or shrinks for each mine next to it
+    pub fn cluster_factor(&self, mines_nearby: i32) -> f64 {
+        CLUSTER_STRENGTH.powf(self.clustering * mines_nearby as f64)
+    }
+}
+
+// Reads a density map, one row of digits per line from 0 for no mines to 9 for the
+// most. The map is stretched to cover the board, so a 3x3 map sets nine regions
+pub fn load_density(path: &Path) -> io::Result<Vec<Vec<f64>>> {
+    let text = fs::read_to_string(path)?;
+    let density: Vec<Vec<f64>> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            l.trim()
+                .chars()
+                .map(|c| c.to_digit(10).map(|d| d as f64))
+                .collect::<Option<Vec<f64>>>()
+        })
+        .collect::<Option<Vec<Vec<f64>>>>()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Density maps are digits"))?;
+
+    let width = density.first().map(|r| r.len()).unwrap_or(0);
+    if width == 0 || density.iter().any(|r| r.len() != width) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Density map rows must be the same length",
+        ));
+    }
+    Ok(density)
+}
+
+// Chances of a mine on each tile, kept in a Fenwick tree so one can be changed, or a
+// tile picked in proportion to its chance, in time logarithmic in the board size
+pub struct ChanceTree {
+    chances: Vec<f64>,
+    // tree[i] holds the sum of chances over the i & -i tiles ending at tile i - 1
+    tree: Vec<f64>,
+}
+
+impl ChanceTree {
+    pub fn new(chances: &[f64]) -> Self {
+        let mut tree = ChanceTree {
+            chances: chances.to_vec(),
+            tree: Vec::new(),
+        };
+        tree.rebuild();
+        tree
+    }
+
+    pub fn set(&mut self, n: usize, chance: f64) {
+        let delta = chance - self.chances[n];
+        self.chances[n] = chance;
+        let mut i = n + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    // A tile picked with probability in proportion to its chance, or None if every
+    // chance is 0
+    pub fn pick(&mut self, rng: &mut impl Rng) -> Option<usize> {
+        for _ in 0..2 {
+            let total = self.total();
+            if total > 0.0 {
+                let n = self.find(rng.gen_range(0.0..total));
+                if self.chances[n] > 0.0 {
+                    return Some(n);
+                }
+            }
+            // Rounding in the running sums can drift, so start them over and try again
+            self.rebuild();
+        }
+        None
+    }
+
+    fn rebuild(&mut self) {
+        self.tree = vec![0.0; self.chances.len() + 1];
+        for n in 0..self.chances.len() {
+            let i = n + 1;
+            self.tree[i] += self.chances[n];
+            let parent = i + (i & i.wrapping_neg());
+            if parent < self.tree.len() {
+                self.tree[parent] += self.tree[i];
+            }
+        }
+    }
+
+    // The sum of every chance
+    pub fn total(&self) -> f64 {
+        let mut sum = 0.0;
+        let mut i = self.chances.len();
+        while i > 0 {
+            sum += self.tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
+
+    // The tile whose share of the running total covers target
+    fn find(&self, mut target: f64) -> usize {
+        let mut n = 0;
+        let mut step = self.tree.len().next_power_of_two();
+        while step > 0 {
+            let next = n + step;
+            if next < self.tree.len() && self.tree[next] <= target {
+                target -= self.tree[next];
+                n = next;
+            }
+            step /= 2;
+        }
+        n.min(self.chances.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn find_covers_each_share() {
+        // Running totals 1, 1, 3, 6
+        let tree = ChanceTree::new(&[1.0, 0.0, 2.0, 3.0]);
+        assert_eq!(tree.total(), 6.0);
+        assert_eq!(tree.find(0.0), 0);
+        assert_eq!(tree.find(0.99), 0);
+        assert_eq!(tree.find(1.0), 2);
+        assert_eq!(tree.find(2.99), 2);
+        assert_eq!(tree.find(3.0), 3);
+        assert_eq!(tree.find(5.99), 3);
+    }
+
+    #[test]
+    fn set_matches_rebuild() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut tree = ChanceTree::new(&[1.0; 37]);
+        for _ in 0..500 {
+            let n = rng.gen_range(0..37);
+            tree.set(n, rng.gen_range(0.0..4.0));
+        }
+        let fresh = ChanceTree::new(&tree.chances);
+        for (a, b) in tree.tree.iter().zip(fresh.tree.iter()) {
+            assert!((a - b).abs() < 1e-9);
+        }
+        tree.rebuild();
+        assert_eq!(tree.tree, fresh.tree);
+    }
+
+    #[test]
+    fn pick_skips_empty_chances() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut tree = ChanceTree::new(&[0.0, 2.0, 0.0, 1.0, 0.0]);
+        for _ in 0..200 {
+            let n = tree.pick(&mut rng).unwrap();
+            assert!(n == 1 || n == 3);
+        }
+        tree.set(1, 0.0);
+        tree.set(3, 0.0);
+        assert_eq!(tree.pick(&mut rng), None);
+    }
+}
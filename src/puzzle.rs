@@ -0,0 +1,185 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::minesweeper;
+use crate::minesweeper::{ClueRule, MinesweeperBoard, Tile};
+use crate::solver;
+use crate::topology::SquareGrid;
+
+// A level pack is a text file of puzzles:
+//
+//   # comment
+//   pack Beginner drills
+//
+//   puzzle First steps
+//   goal safe
+//   oo.*
+//   o.F.
+//
+// goal is "safe" to win by revealing every safe tile, or "clear" to win the same way
+// but lose on any click the numbers didn't prove safe. In the board rows, '.' is a
+// hidden safe tile, '*' a hidden mine, 'o' a revealed safe tile and 'F' a flagged mine
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Goal {
+    FindSafeTiles,
+    NoGuessing,
+}
+
+pub struct Puzzle {
+    pub name: String,
+    pub goal: Goal,
+    pub board: MinesweeperBoard,
+    pub mine_count: i32,
+}
+
+impl Puzzle {
+    pub fn topology(&self) -> SquareGrid {
+        SquareGrid::new(self.board.width(), self.board.height())
+    }
+
+    // Mines left once the pre-placed flags are counted
+    pub fn unflagged_mines(&self) -> i32 {
+        let flags: i32 = self.board.tiles().map(|t| t.get_flags()).sum();
+        self.mine_count - flags
+    }
+}
+
+pub struct PuzzlePack {
+    pub name: String,
+    pub puzzles: Vec<Puzzle>,
+}
+
+pub fn load_pack(path: &Path) -> io::Result<PuzzlePack> {
+    parse_pack(&fs::read_to_string(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn parse_pack(text: &str) -> Result<PuzzlePack, String> {
+    let mut pack = PuzzlePack {
+        name: String::new(),
+        puzzles: Vec::new(),
+    };
+    // Name, goal and rows of the puzzle being read
+    let mut current: Option<(String, Goal, Vec<&str>)> = None;
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+        match keyword {
+            "" => {}
+            _ if keyword.starts_with('#') => {}
+            "pack" => pack.name = rest.trim().to_string(),
+            "puzzle" => {
+                if let Some(puzzle) = current.take() {
+                    pack.puzzles.push(build_puzzle(puzzle)?);
+                }
+                current = Some((rest.trim().to_string(), Goal::FindSafeTiles, Vec::new()));
+            }
+            "goal" => {
+                let puzzle = current
+                    .as_mut()
+                    .ok_or(format!("Line {}: goal outside a puzzle", n + 1))?;
+                puzzle.1 = match rest.trim() {
+                    "safe" => Goal::FindSafeTiles,
+                    "clear" => Goal::NoGuessing,
+                    other => return Err(format!("Line {}: unknown goal {}", n + 1, other)),
+                };
+            }
+            _ => {
+                let puzzle = current
+                    .as_mut()
+                    .ok_or(format!("Line {}: board row outside a puzzle", n + 1))?;
+                if let Some(c) = line.chars().find(|c| !".*oF".contains(*c)) {
+                    return Err(format!("Line {}: unknown tile '{}'", n + 1, c));
+                }
+                if puzzle.2.first().map(|r| r.len() != line.len()) == Some(true) {
+                    return Err(format!("Line {}: rows differ in length", n + 1));
+                }
+                puzzle.2.push(line);
+            }
+        }
+    }
+    if let Some(puzzle) = current.take() {
+        pack.puzzles.push(build_puzzle(puzzle)?);
+    }
+
+    if pack.puzzles.is_empty() {
+        return Err("Pack has no puzzles".to_string());
+    }
+    Ok(pack)
+}
+
+fn build_puzzle((name, goal, rows): (String, Goal, Vec<&str>)) -> Result<Puzzle, String> {
+    if rows.is_empty() {
+        return Err(format!("Puzzle {} has no board", name));
+    }
+
+    let mut mine_count = 0;
+    let mut board = MinesweeperBoard::new(rows[0].len() as i32, rows.len() as i32);
+    for (j, row) in rows.iter().enumerate() {
+        for (i, c) in row.chars().enumerate() {
+            let tile = match c {
+                '*' => Tile::new(1, 0, 0, 0, false, false),
+                'F' => Tile::new(1, 0, 0, 1, false, false),
+                'o' => Tile::new(0, 0, 0, 0, false, true),
+                _ => Tile::new(0, 0, 0, 0, false, false),
+            };
+            board.set_tile(i as i32, j as i32, tile);
+            if c == '*' || c == 'F' {
+                mine_count += 1;
+            }
+        }
+    }
+
+    let topology = SquareGrid::new(board.width(), board.height());
+    minesweeper::determine_tile_number(
+        &mut board,
+        &topology,
+        ClueRule::Standard,
+        1,
+        &mut rand::thread_rng(),
+    );
+
+    Ok(Puzzle {
+        name,
+        goal,
+        board,
+        mine_count,
+    })
+}
+
+// Checks the numbers shown at the start pin down every mine, by solving the puzzle
+// with logic alone
+pub fn validate(puzzle: &Puzzle) -> Result<(), String> {
+    if puzzle.board.tiles().all(|t| !t.is_revealed()) {
+        return Err("nothing is revealed".to_string());
+    }
+    if !solver::solves(
+        &puzzle.board,
+        &puzzle.topology(),
+        ClueRule::Standard,
+        1,
+        puzzle.mine_count,
+    ) {
+        return Err("can't be solved without guessing".to_string());
+    }
+    Ok(())
+}
+
+// Solved puzzles are kept next to the pack, one name per line
+pub fn progress_path(pack_path: &Path) -> PathBuf {
+    pack_path.with_extension("progress")
+}
+
+pub fn load_progress(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .map(|text| text.lines().map(|l| l.to_string()).collect())
+        .unwrap_or_default()
+}
+
+pub fn save_progress(path: &Path, solved: &[String]) -> io::Result<()> {
+    fs::write(path, solved.join("\n") + "\n")
+}
@@ -0,0 +1,209 @@
+// Python bindings, built with the python feature:
+//
+//   maturin develop --release
+//   pytest python/tests
+//
+// Boards and probabilities come back as memoryviews shaped (height, width), which
+// numpy.asarray wraps without copying again
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyMemoryView};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::game::{Game, Status};
+use crate::minesweeper::ClueRule;
+use crate::solver;
+use crate::visible::VisibleTile;
+
+// Values in Game.view() for tiles that don't show a number
+const HIDDEN: i8 = -1;
+const FLAGGED: i8 = -2;
+const QUESTIONED: i8 = -3;
+const MINE: i8 = -4;
+const EXPLODED: i8 = -5;
+const WRONG_FLAG: i8 = -6;
+
+type Tiles = Vec<(i32, i32)>;
+
+// A single player game on a square board
+#[pyclass(name = "Game")]
+pub struct PyGame {
+    game: Game,
+}
+
+#[pymethods]
+impl PyGame {
+    // The same seed always gives the same board. Without one the board is random
+    #[new]
+    #[pyo3(signature = (width, height, mines, seed = None, max_per_tile = 1))]
+    fn new(
+        width: i32,
+        height: i32,
+        mines: i32,
+        seed: Option<u64>,
+        max_per_tile: i32,
+    ) -> PyResult<Self> {
+        if width <= 0 || height <= 0 || max_per_tile <= 0 {
+            return Err(PyValueError::new_err("Sizes must be positive"));
+        }
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let game = Game::new(width, height, mines, max_per_tile, &mut rng)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyGame { game })
+    }
+
+    #[getter]
+    fn width(&self) -> i32 {
+        self.game.width()
+    }
+
+    #[getter]
+    fn height(&self) -> i32 {
+        self.game.height()
+    }
+
+    #[getter]
+    fn mine_count(&self) -> i32 {
+        self.game.mine_count()
+    }
+
+    // Mines less flags placed, as the counter shows
+    #[getter]
+    fn mines_left(&self) -> i32 {
+        self.game.mines_left()
+    }
+
+    // "playing", "lost" or "won"
+    #[getter]
+    fn status(&self) -> &'static str {
+        match self.game.status() {
+            Status::Playing => "playing",
+            Status::Lost => "lost",
+            Status::Won => "won",
+        }
+    }
+
+    // Reveals the tile, returning the (x, y) of every tile opened. Revealing a mine
+    // loses the game
+    fn reveal(&mut self, x: i32, y: i32) -> PyResult<Tiles> {
+        self.check(x, y)?;
+        Ok(pairs(self.game.reveal(x, y)))
+    }
+
+    // Reveals the neighbours of a revealed number with as many flags around it
+    fn chord(&mut self, x: i32, y: i32) -> PyResult<Tiles> {
+        self.check(x, y)?;
+        Ok(pairs(self.game.chord(x, y)))
+    }
+
+    // Adds a flag to a hidden tile, or clears them once it holds max_per_tile,
+    // returning the flags now on it
+    fn flag(&mut self, x: i32, y: i32) -> PyResult<i32> {
+        self.check(x, y)?;
+        Ok(self.game.flag(x, y))
+    }
+
+    // What the player sees, as int8 shaped (height, width): the number on revealed
+    // tiles, -1 hidden, -2 flagged and -3 question marked. Once the game is over, -4
+    // marks the unflagged mines, -5 the one that was hit and -6 wrong flags
+    fn view<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let data: Vec<u8> = self
+            .game
+            .view()
+            .tiles()
+            .map(|tile| {
+                let value = match tile {
+                    VisibleTile::Revealed(num) => num.min(i8::MAX as i32) as i8,
+                    VisibleTile::Hidden => HIDDEN,
+                    VisibleTile::Flagged(_) => FLAGGED,
+                    VisibleTile::Questioned => QUESTIONED,
+                    VisibleTile::Mine(_) => MINE,
+                    VisibleTile::Exploded => EXPLODED,
+                    VisibleTile::WrongFlag => WRONG_FLAG,
+                };
+                value as u8
+            })
+            .collect();
+        self.shaped(py, &data, "b")
+    }
+
+    // The fewest clicks that clear the board
+    fn three_bv(&self) -> i32 {
+        self.game.three_bv()
+    }
+
+    fn revealed_safe_tiles(&self) -> i32 {
+        self.game.revealed_safe_tiles()
+    }
+
+    // Tiles the revealed numbers prove safe and prove hold a mine, as two lists of
+    // (x, y)
+    fn deduce(&self) -> (Tiles, Tiles) {
+        let deductions = solver::deduce(
+            &self.game.view(),
+            self.game.topology(),
+            ClueRule::Standard,
+            self.game.max_per_tile(),
+            self.game.mine_count(),
+        );
+        (pairs(deductions.safe), pairs(deductions.mines))
+    }
+
+    // Chance of a mine on each tile as float64 shaped (height, width), -1.0 on
+    // revealed tiles
+    fn probabilities<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let width = self.game.width();
+        let mut chances = vec![-1.0_f64; (width * self.game.height()) as usize];
+        for ([x, y], chance) in solver::probabilities(
+            &self.game.view(),
+            self.game.topology(),
+            ClueRule::Standard,
+            self.game.max_per_tile(),
+            self.game.mine_count(),
+        ) {
+            chances[(y * width + x) as usize] = chance;
+        }
+        let data: Vec<u8> = chances.iter().flat_map(|c| c.to_ne_bytes()).collect();
+        self.shaped(py, &data, "d")
+    }
+}
+
+impl PyGame {
+    fn check(&self, x: i32, y: i32) -> PyResult<()> {
+        if self.game.contains(x, y) {
+            Ok(())
+        } else {
+            Err(PyIndexError::new_err(format!(
+                "({}, {}) is off the board",
+                x, y
+            )))
+        }
+    }
+
+    // data as a memoryview of format items shaped (height, width)
+    fn shaped<'py>(
+        &self,
+        py: Python<'py>,
+        data: &[u8],
+        format: &str,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let bytes = PyBytes::new(py, data);
+        PyMemoryView::from(&bytes)?
+            .call_method1("cast", (format, (self.game.height(), self.game.width())))
+    }
+}
+
+fn pairs(tiles: Vec<[i32; 2]>) -> Tiles {
+    tiles.into_iter().map(|[x, y]| (x, y)).collect()
+}
+
+#[pymodule]
+#[pyo3(name = "minesweeper")]
+fn minesweeper_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGame>()?;
+    Ok(())
+}
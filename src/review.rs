@@ -0,0 +1,328 @@
+use crate::minesweeper::{ClueRule, MinesweeperBoard};
+use crate::solver;
+use crate::topology::Topology;
+use crate::visible::VisibleBoard;
+
+// What a review makes of one move
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Verdict {
+    // Nothing was proven safe, so some tile had to be guessed
+    ForcedGuess,
+    // Opened a tile that wasn't proven safe while another tile was
+    AvoidableGuess,
+    // Opened a tile the numbers proved held a mine
+    ProvenMine,
+    // Flagged a tile without a mine
+    WrongFlag,
+}
+
+// A move a review picks out, counting moves from 1, and the tile it went wrong on
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Finding {
+    pub move_number: usize,
+    pub position: [i32; 2],
+    pub verdict: Verdict,
+}
+
+impl Finding {
+    // Whether the move went against what the numbers showed. A wrong flag only shows
+    // a misread, and is counted on its own
+    pub fn is_error(&self) -> bool {
+        matches!(self.verdict, Verdict::AvoidableGuess | Verdict::ProvenMine)
+    }
+}
+
+// A finished game looked back over with the solver
+pub struct Review {
+    // The move that hit the mine, judged on the board just before it
+    pub fatal: Option<Finding>,
+    // The first move that went against what the numbers showed
+    pub first_error: Option<Finding>,
+    pub first_wrong_flag: Option<Finding>,
+    pub wrong_flags: i32,
+    pub missed_mines: i32,
+}
+
+// Replays a game from history: every board it went through from the start, each with
+// the tile clicked to reach it. hit_mine is the mine that ended a lost game.
+// mine_count is every mine on the board, flagged or not
+pub fn review(
+    history: &[(MinesweeperBoard, Option<[i32; 2]>)],
+    hit_mine: Option<[i32; 2]>,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+    mine_count: i32,
+) -> Review {
+    let mut fatal = None;
+    let mut first_error = None;
+    let mut first_wrong_flag = None;
+
+    for (n, pair) in history.windows(2).enumerate() {
+        let hit = hit_mine.filter(|_| n + 2 == history.len());
+        let finding = judge(
+            &pair[0].0,
+            &pair[1],
+            hit,
+            topology,
+            clue_rule,
+            max_per_tile,
+            mine_count,
+        )
+        .map(|(verdict, position)| Finding {
+            move_number: n + 1,
+            position,
+            verdict,
+        });
+        if let Some(finding) = finding {
+            if first_error.is_none() && finding.is_error() {
+                first_error = Some(finding);
+            }
+            if first_wrong_flag.is_none() && finding.verdict == Verdict::WrongFlag {
+                first_wrong_flag = Some(finding);
+            }
+            if hit.is_some() {
+                fatal = Some(finding);
+            }
+        }
+    }
+
+    let mut review = Review {
+        fatal,
+        first_error,
+        first_wrong_flag,
+        wrong_flags: 0,
+        missed_mines: 0,
+    };
+    if let Some((board, _)) = history.last() {
+        for [x, y] in topology.positions() {
+            if board.is_revealed(x, y) || hit_mine == Some([x, y]) {
+                continue;
+            }
+            if board.get_flags(x, y) > board.get_mines(x, y) {
+                review.wrong_flags += 1;
+            } else if board.has_mine(x, y) && !board.is_flagged(x, y) {
+                review.missed_mines += 1;
+            }
+        }
+    }
+    review
+}
+
+// Judges only the move that hit the mine, which is all a lost game needs until the full
+// review is asked for
+pub fn fatal_move(
+    history: &[(MinesweeperBoard, Option<[i32; 2]>)],
+    hit_mine: Option<[i32; 2]>,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+    mine_count: i32,
+) -> Option<Finding> {
+    let hit = hit_mine?;
+    let [.., (before, _), last] = history else {
+        return None;
+    };
+    let (verdict, position) = judge(
+        before,
+        last,
+        Some(hit),
+        topology,
+        clue_rule,
+        max_per_tile,
+        mine_count,
+    )?;
+    Some(Finding {
+        move_number: history.len() - 1,
+        position,
+        verdict,
+    })
+}
+
+// What one move from before to after deserves, and the tile it went wrong on. hit is
+// the mine the move hit, if it did
+fn judge(
+    before: &MinesweeperBoard,
+    (after, click): &(MinesweeperBoard, Option<[i32; 2]>),
+    hit: Option<[i32; 2]>,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+    mine_count: i32,
+) -> Option<(Verdict, [i32; 2])> {
+    let positions = topology.positions();
+    let flagged: Vec<[i32; 2]> = positions
+        .iter()
+        .copied()
+        .filter(|[x, y]| after.get_flags(*x, *y) > before.get_flags(*x, *y))
+        .collect();
+    let mut opened: Vec<[i32; 2]> = positions
+        .iter()
+        .copied()
+        .filter(|[x, y]| after.is_revealed(*x, *y) && !before.is_revealed(*x, *y))
+        .collect();
+    opened.extend(hit);
+
+    if let Some(&[x, y]) = flagged
+        .iter()
+        .find(|[x, y]| after.get_flags(*x, *y) > after.get_mines(*x, *y))
+    {
+        return Some((Verdict::WrongFlag, [x, y]));
+    }
+    if opened.is_empty() {
+        return None;
+    }
+
+    // The tiles the player chose to open, rather than those a cascade opened
+    let targets = match (hit, *click) {
+        (Some(mine), _) => vec![mine],
+        (None, Some([x, y])) if before.is_revealed(x, y) => topology
+            .neighbours(x, y)
+            .into_iter()
+            .filter(|[nx, ny]| !before.is_revealed(*nx, *ny) && !before.is_flagged(*nx, *ny))
+            .collect(),
+        (None, Some(tile)) => vec![tile],
+        (None, None) => opened,
+    };
+    let deductions = solver::deduce(
+        &VisibleBoard::new(before),
+        topology,
+        clue_rule,
+        max_per_tile,
+        mine_count,
+    );
+    if let Some(&tile) = targets.iter().find(|t| deductions.mines.contains(t)) {
+        Some((Verdict::ProvenMine, tile))
+    } else if let Some(&tile) = targets.iter().find(|t| !deductions.safe.contains(t)) {
+        if deductions.safe.is_empty() {
+            Some((Verdict::ForcedGuess, tile))
+        } else {
+            Some((Verdict::AvoidableGuess, tile))
+        }
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::minesweeper;
+    use crate::topology::SquareGrid;
+
+    // A one row board with mines where the row has '*'
+    fn board(row: &str) -> (MinesweeperBoard, SquareGrid) {
+        let topology = SquareGrid::new(row.len() as i32, 1);
+        let mut board = MinesweeperBoard::new(row.len() as i32, 1);
+        for (x, c) in row.chars().enumerate() {
+            if c == '*' {
+                board.set_mine(x as i32, 0);
+            }
+        }
+        minesweeper::determine_tile_number(
+            &mut board,
+            &topology,
+            ClueRule::Standard,
+            1,
+            &mut StdRng::seed_from_u64(0),
+        );
+        (board, topology)
+    }
+
+    type History = Vec<(MinesweeperBoard, Option<[i32; 2]>)>;
+
+    // Plays the clicks in turn, a flag where flag is set, returning the history and
+    // the mine hit
+    fn play(
+        mut board: MinesweeperBoard,
+        topology: &SquareGrid,
+        clicks: &[([i32; 2], bool)],
+    ) -> (History, Option<[i32; 2]>) {
+        let mut history = vec![(board.clone(), None)];
+        let mut hit = None;
+        for &([x, y], flag) in clicks {
+            if flag {
+                minesweeper::flag_tile(&mut board, x, y, 0, false, 1);
+            } else {
+                hit = minesweeper::reveal_tile(&mut board, topology, x, y).err();
+            }
+            history.push((board.clone(), Some([x, y])));
+        }
+        (history, hit)
+    }
+
+    fn finding(move_number: usize, position: [i32; 2], verdict: Verdict) -> Option<Finding> {
+        Some(Finding {
+            move_number,
+            position,
+            verdict,
+        })
+    }
+
+    #[test]
+    fn first_click_is_a_forced_guess() {
+        let (board, topology) = board("*.");
+        let (history, hit) = play(board, &topology, &[([0, 0], false)]);
+        let review = review(&history, hit, &topology, ClueRule::Standard, 1, 1);
+        assert_eq!(review.fatal, finding(1, [0, 0], Verdict::ForcedGuess));
+        assert_eq!(review.first_error, None);
+        assert_eq!(review.missed_mines, 0);
+    }
+
+    #[test]
+    fn avoidable_guess_then_proven_mine() {
+        // With the 1 showing, the mine count proves the far tile safe, so opening the
+        // tile beside the 1 was a needless guess. After that the mine is proven
+        let (mut board, topology) = board("*...");
+        board.reveal(1, 0);
+        let (history, hit) = play(board, &topology, &[([2, 0], false), ([0, 0], false)]);
+        let review = review(&history, hit, &topology, ClueRule::Standard, 1, 1);
+        assert_eq!(
+            review.first_error,
+            finding(1, [2, 0], Verdict::AvoidableGuess)
+        );
+        assert_eq!(review.fatal, finding(2, [0, 0], Verdict::ProvenMine));
+        assert_eq!(
+            fatal_move(&history, hit, &topology, ClueRule::Standard, 1, 1),
+            review.fatal
+        );
+    }
+
+    #[test]
+    fn proven_moves_are_not_findings() {
+        let (mut board, topology) = board("*...");
+        board.reveal(1, 0);
+        let (history, hit) = play(board, &topology, &[([3, 0], false), ([0, 0], true)]);
+        let review = review(&history, hit, &topology, ClueRule::Standard, 1, 1);
+        assert_eq!(review.first_error, None);
+        assert_eq!(review.first_wrong_flag, None);
+        assert_eq!(review.fatal, None);
+        assert_eq!(
+            fatal_move(&history, hit, &topology, ClueRule::Standard, 1, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn wrong_flags_are_counted_apart_from_logic_errors() {
+        let (mut board, topology) = board("*...");
+        board.reveal(1, 0);
+        let (history, hit) = play(board, &topology, &[([3, 0], true), ([0, 0], false)]);
+        let review = review(&history, hit, &topology, ClueRule::Standard, 1, 1);
+        assert_eq!(
+            review.first_wrong_flag,
+            finding(1, [3, 0], Verdict::WrongFlag)
+        );
+        assert_eq!(review.wrong_flags, 1);
+        // Flags prove nothing, so the far tile was still known safe when the mine was
+        // opened, and that is the first logic error
+        assert_eq!(
+            review.first_error,
+            finding(2, [0, 0], Verdict::AvoidableGuess)
+        );
+        assert_eq!(review.fatal, review.first_error);
+    }
+}
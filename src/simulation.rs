@@ -0,0 +1,355 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::minesweeper;
+use crate::minesweeper::ClueRule;
+use crate::placement::Placement;
+use crate::solver;
+use crate::solver::Deductions;
+use crate::topology::{SquareGrid, Topology};
+use crate::visible::VisibleBoard;
+
+// z for a 95% confidence interval
+const Z_95: f64 = 1.96;
+
+// A board size and mine count to play many games of
+#[derive(Copy, Clone)]
+pub struct Configuration {
+    pub width: i32,
+    pub height: i32,
+    pub mine_count: i32,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Action {
+    Reveal,
+    Chord,
+    Flag,
+}
+
+// One click a strategy wants made. guess marks a reveal the strategy couldn't prove
+// safe
+#[derive(Copy, Clone)]
+pub struct Move {
+    pub action: Action,
+    pub x: i32,
+    pub y: i32,
+    pub guess: bool,
+}
+
+// A way of playing. Strategies only see the board as the player does, and are shared
+// by every thread, so keep per game state in the board rather than in the strategy
+pub trait Strategy: Sync {
+    fn name(&self) -> &str;
+
+    // The next clicks to make, played in order until one ends the game. An empty list
+    // gives up, which counts as a loss
+    fn moves(
+        &self,
+        board: &VisibleBoard,
+        topology: &dyn Topology,
+        mine_count: i32,
+        rng: &mut StdRng,
+    ) -> Vec<Move>;
+}
+
+// Clicks a random hidden tile every move
+pub struct RandomClicker;
+
+impl Strategy for RandomClicker {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn moves(
+        &self,
+        board: &VisibleBoard,
+        topology: &dyn Topology,
+        _mine_count: i32,
+        rng: &mut StdRng,
+    ) -> Vec<Move> {
+        random_guess(board, topology, rng).into_iter().collect()
+    }
+}
+
+// Reveals every tile the solver proves safe and flags every proven mine, guessing at
+// random only when nothing is proven
+pub struct SolverPlayer;
+
+impl Strategy for SolverPlayer {
+    fn name(&self) -> &str {
+        "solver"
+    }
+
+    fn moves(
+        &self,
+        board: &VisibleBoard,
+        topology: &dyn Topology,
+        mine_count: i32,
+        rng: &mut StdRng,
+    ) -> Vec<Move> {
+        let deductions = solver::deduce(board, topology, ClueRule::Standard, 1, mine_count);
+        let mut moves = proven_moves(board, &deductions);
+
+        if deductions.safe.is_empty() {
+            moves.extend(random_guess(board, topology, rng));
+        }
+        moves
+    }
+}
+
+// Flags on the proven mines not yet flagged, then reveals of the unflagged tiles proven
+// safe
+pub fn proven_moves(board: &VisibleBoard, deductions: &Deductions) -> Vec<Move> {
+    let flags = deductions.mines.iter().map(|&p| (Action::Flag, p));
+    let reveals = deductions.safe.iter().map(|&p| (Action::Reveal, p));
+    flags
+        .chain(reveals)
+        .filter(|(_, [x, y])| !board.is_flagged(*x, *y))
+        .map(|(action, [x, y])| Move {
+            action,
+            x,
+            y,
+            guess: false,
+        })
+        .collect()
+}
+
+// A hidden, unflagged tile picked at random
+pub fn random_guess(
+    board: &VisibleBoard,
+    topology: &dyn Topology,
+    rng: &mut StdRng,
+) -> Option<Move> {
+    let hidden: Vec<[i32; 2]> = topology
+        .positions()
+        .into_iter()
+        .filter(|[x, y]| !board.is_revealed(*x, *y) && !board.is_flagged(*x, *y))
+        .collect();
+    if hidden.is_empty() {
+        return None;
+    }
+    let [x, y] = hidden[rng.gen_range(0..hidden.len())];
+    Some(Move {
+        action: Action::Reveal,
+        x,
+        y,
+        guess: true,
+    })
+}
+
+// How one game went
+#[derive(Copy, Clone)]
+pub struct GameRecord {
+    pub seed: u64,
+    pub won: bool,
+    pub guesses: i32,
+    pub clicks: i32,
+    pub bbbv: i32,
+}
+
+// Plays the game for seed to the end. The board depends only on the seed, so every
+// strategy meets the same boards
+pub fn play_game(configuration: Configuration, strategy: &dyn Strategy, seed: u64) -> GameRecord {
+    let topology = SquareGrid::new(configuration.width, configuration.height);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = minesweeper::build_minesweeper_board(
+        &topology,
+        configuration.mine_count,
+        1,
+        ClueRule::Standard,
+        &Placement::default(),
+        &mut rng,
+    )
+    .expect("Configuration holds too many mines");
+
+    let mut record = GameRecord {
+        seed,
+        won: false,
+        guesses: 0,
+        clicks: 0,
+        bbbv: minesweeper::three_bv(&board, &topology),
+    };
+    let safe_tiles = configuration.width * configuration.height - configuration.mine_count;
+    // A strategy that never finishes is stopped after a few clicks per tile
+    let click_limit = configuration.width * configuration.height * 4;
+
+    while record.clicks < click_limit {
+        let moves = strategy.moves(
+            &VisibleBoard::new(&board),
+            &topology,
+            configuration.mine_count,
+            &mut rng,
+        );
+        if moves.is_empty() {
+            return record;
+        }
+
+        for m in moves {
+            record.clicks += 1;
+            record.guesses += m.guess as i32;
+            let result = match m.action {
+                Action::Reveal => minesweeper::reveal_tile(&mut board, &topology, m.x, m.y),
+                Action::Chord => {
+                    minesweeper::cord_tile(&mut board, &topology, ClueRule::Standard, m.x, m.y)
+                }
+                Action::Flag => {
+                    minesweeper::flag_tile(&mut board, m.x, m.y, 0, false, 1);
+                    Ok(Vec::new())
+                }
+            };
+            if result.is_err() {
+                return record;
+            }
+            if minesweeper::revealed_safe_tiles(&board) == safe_tiles {
+                record.won = true;
+                return record;
+            }
+        }
+    }
+    record
+}
+
+// Plays games seeded first_seed, first_seed + 1, ... on threads threads, returning the
+// records in seed order
+pub fn simulate(
+    configuration: Configuration,
+    strategy: &dyn Strategy,
+    games: usize,
+    first_seed: u64,
+    threads: usize,
+) -> Vec<GameRecord> {
+    let next = AtomicUsize::new(0);
+    let records: Mutex<Vec<Option<GameRecord>>> = Mutex::new(vec![None; games]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let n = next.fetch_add(1, Ordering::Relaxed);
+                if n >= games {
+                    break;
+                }
+                let record = play_game(configuration, strategy, first_seed + n as u64);
+                records.lock().unwrap()[n] = Some(record);
+            });
+        }
+    });
+
+    records
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// Totals over many games, with 95% confidence intervals
+pub struct Summary {
+    pub strategy: String,
+    pub configuration: Configuration,
+    pub games: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+    // Wilson score interval on the win rate
+    pub win_low: f64,
+    pub win_high: f64,
+    pub mean_guesses: f64,
+    // Half the width of the interval on mean_guesses
+    pub guesses_margin: f64,
+    pub mean_bbbv: f64,
+    pub mean_clicks: f64,
+    // 3BV per click over won games, 1.0 being a perfect clear
+    pub efficiency: f64,
+}
+
+pub fn summarize(strategy: &str, configuration: Configuration, records: &[GameRecord]) -> Summary {
+    let games = records.len();
+    let n = games.max(1) as f64;
+    let wins = records.iter().filter(|r| r.won).count();
+    let win_rate = wins as f64 / n;
+
+    let spread = Z_95 * (win_rate * (1.0 - win_rate) / n + Z_95 * Z_95 / (4.0 * n * n)).sqrt();
+    let centre = win_rate + Z_95 * Z_95 / (2.0 * n);
+    let scale = 1.0 + Z_95 * Z_95 / n;
+
+    let mean_guesses = records.iter().map(|r| r.guesses as f64).sum::<f64>() / n;
+    let variance = records
+        .iter()
+        .map(|r| (r.guesses as f64 - mean_guesses).powi(2))
+        .sum::<f64>()
+        / (n - 1.0).max(1.0);
+
+    let won_bbbv: i32 = records.iter().filter(|r| r.won).map(|r| r.bbbv).sum();
+    let won_clicks: i32 = records.iter().filter(|r| r.won).map(|r| r.clicks).sum();
+
+    Summary {
+        strategy: strategy.to_string(),
+        configuration,
+        games,
+        wins,
+        win_rate,
+        win_low: ((centre - spread) / scale).max(0.0),
+        win_high: ((centre + spread) / scale).min(1.0),
+        mean_guesses,
+        guesses_margin: Z_95 * (variance / n).sqrt(),
+        mean_bbbv: records.iter().map(|r| r.bbbv as f64).sum::<f64>() / n,
+        mean_clicks: records.iter().map(|r| r.clicks as f64).sum::<f64>() / n,
+        efficiency: if won_clicks > 0 {
+            won_bbbv as f64 / won_clicks as f64
+        } else {
+            0.0
+        },
+    }
+}
+
+pub const CSV_HEADER: &str = "strategy,width,height,mines,games,wins,win_rate,win_low,win_high,\
+mean_guesses,guesses_margin,mean_bbbv,mean_clicks,efficiency";
+
+impl Summary {
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.4},{:.4},{:.2},{:.2},{:.4}",
+            self.strategy,
+            self.configuration.width,
+            self.configuration.height,
+            self.configuration.mine_count,
+            self.games,
+            self.wins,
+            self.win_rate,
+            self.win_low,
+            self.win_high,
+            self.mean_guesses,
+            self.guesses_margin,
+            self.mean_bbbv,
+            self.mean_clicks,
+            self.efficiency
+        )
+    }
+
+    pub fn json(&self) -> String {
+        format!(
+            "{{\"strategy\": \"{}\", \"width\": {}, \"height\": {}, \"mines\": {}, \
+             \"games\": {}, \"wins\": {}, \"win_rate\": {:.4}, \"win_low\": {:.4}, \
+             \"win_high\": {:.4}, \"mean_guesses\": {:.4}, \"guesses_margin\": {:.4}, \
+             \"mean_bbbv\": {:.2}, \"mean_clicks\": {:.2}, \"efficiency\": {:.4}}}",
+            self.strategy.replace('\\', "\\\\").replace('"', "\\\""),
+            self.configuration.width,
+            self.configuration.height,
+            self.configuration.mine_count,
+            self.games,
+            self.wins,
+            self.win_rate,
+            self.win_low,
+            self.win_high,
+            self.mean_guesses,
+            self.guesses_margin,
+            self.mean_bbbv,
+            self.mean_clicks,
+            self.efficiency
+        )
+    }
+}
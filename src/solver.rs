@@ -0,0 +1,685 @@
+use std::collections::HashMap;
+
+use crate::minesweeper;
+use crate::minesweeper::{ClueRule, MinesweeperBoard};
+use crate::topology::Topology;
+use crate::visible::VisibleBoard;
+
+// Search steps spent trying to list every layout of a group of linked tiles, and then
+// on each question about a single tile when there were too many
+const LIST_LIMIT: usize = 50_000;
+const QUERY_LIMIT: usize = 50_000;
+
+// What the revealed numbers prove about the hidden tiles
+pub struct Deductions {
+    pub safe: Vec<[i32; 2]>,
+    pub mines: Vec<[i32; 2]>,
+}
+
+// A revealed number's claim about the hidden tiles around it: the mines on cells add
+// up to one of counts
+struct Constraint {
+    cells: Vec<usize>,
+    counts: Vec<i32>,
+}
+
+// Finds every hidden tile that is safe, or holds a mine, in all layouts that agree
+// with the revealed numbers and the mine count. Only looks at what the player can
+// see, and ignores flags since they may be wrong
+pub fn deduce(
+    board: &VisibleBoard,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+    mine_count: i32,
+) -> Deductions {
+    let Frontier {
+        cells,
+        index,
+        constraints,
+    } = read_constraints(board, topology, clue_rule);
+    let watching = watching(cells.len(), &constraints);
+
+    let mut deductions = Deductions {
+        safe: Vec::new(),
+        mines: Vec::new(),
+    };
+    // Fewest and most mines the numbered area can hold
+    let mut least = 0;
+    let mut most = 0;
+
+    for group in groups(cells.len(), &constraints) {
+        let mut search = Search::new(&group, &constraints, &watching, max_per_tile, false);
+
+        match search.run(0) {
+            // A group with no layout at all means the numbers contradict each other
+            Some(_) if search.least > search.most => {
+                most += group.len() as i32 * max_per_tile;
+            }
+            Some(_) => {
+                for &cell in group.iter() {
+                    if !search.can_be_mine[cell] {
+                        deductions.safe.push(cells[cell]);
+                    } else if !search.can_be_safe[cell] {
+                        deductions.mines.push(cells[cell]);
+                    }
+                }
+                least += search.least;
+                most += search.most;
+            }
+            // Too many layouts to list, so look for one layout per question instead
+            None => {
+                most += group.len() as i32 * max_per_tile;
+                search.first_only = true;
+                for &cell in group.iter() {
+                    if !search.can_be_safe[cell] && search.ask(cell, (0, 0)) == Some(false) {
+                        deductions.mines.push(cells[cell]);
+                    } else if !search.can_be_mine[cell]
+                        && search.ask(cell, (1, max_per_tile)) == Some(false)
+                    {
+                        deductions.safe.push(cells[cell]);
+                    }
+                }
+            }
+        }
+    }
+
+    // Hidden tiles no number touches can only be settled by the mine count
+    let loose: Vec<[i32; 2]> = topology
+        .positions()
+        .into_iter()
+        .filter(|[x, y]| !board.is_revealed(*x, *y))
+        .filter(|p| !index.contains_key(p))
+        .collect();
+    if least == mine_count {
+        deductions.safe.extend(loose);
+    } else if !loose.is_empty() && mine_count - most == loose.len() as i32 * max_per_tile {
+        deductions.mines.extend(loose);
+    }
+
+    deductions
+}
+
+// Plays the board using nothing but deduce, returning whether that was enough to
+// reveal every safe tile
+pub fn solves(
+    board: &MinesweeperBoard,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+    mine_count: i32,
+) -> bool {
+    let mut board = board.clone();
+    board.clear_flags();
+    let safe_tiles = topology
+        .positions()
+        .iter()
+        .filter(|[x, y]| !board.has_mine(*x, *y))
+        .count() as i32;
+
+    loop {
+        if minesweeper::revealed_safe_tiles(&board) == safe_tiles {
+            return true;
+        }
+
+        let deductions = deduce(
+            &VisibleBoard::new(&board),
+            topology,
+            clue_rule,
+            max_per_tile,
+            mine_count,
+        );
+        if deductions.safe.is_empty() {
+            return false;
+        }
+        for [x, y] in deductions.safe {
+            if minesweeper::reveal_tile(&mut board, topology, x, y).is_err() {
+                return false;
+            }
+        }
+    }
+}
+
+// Chance of a mine on each hidden tile, counting every layout that agrees with the
+// revealed numbers and the mine count as equally likely. Groups with too many layouts
+// to list are estimated from the ones visited
+pub fn probabilities(
+    board: &VisibleBoard,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+    max_per_tile: i32,
+    mine_count: i32,
+) -> Vec<([i32; 2], f64)> {
+    let Frontier {
+        cells,
+        index,
+        constraints,
+    } = read_constraints(board, topology, clue_rule);
+    let watching = watching(cells.len(), &constraints);
+    let loose: Vec<[i32; 2]> = topology
+        .positions()
+        .into_iter()
+        .filter(|[x, y]| !board.is_revealed(*x, *y))
+        .filter(|p| !index.contains_key(p))
+        .collect();
+
+    // Each group's layout weights, scaled to at most 1 since only ratios matter
+    let mut tallies = Vec::new();
+    for group in groups(cells.len(), &constraints) {
+        let mut search = Search::new(&group, &constraints, &watching, max_per_tile, true);
+        search.run(0);
+        let scale = search.weights.iter().cloned().fold(0.0, f64::max);
+        if scale == 0.0 {
+            // The numbers contradict each other, so say nothing about this group
+            search.weights[0] = 1.0;
+        } else {
+            for w in search
+                .weights
+                .iter_mut()
+                .chain(search.mine_weights.iter_mut().flatten())
+            {
+                *w /= scale;
+            }
+        }
+        let Search {
+            weights,
+            mine_weights,
+            ..
+        } = search;
+        tallies.push((group, weights, mine_weights));
+    }
+
+    // Weights of the numbered area's total over the groups before and after each one
+    let mut before = vec![vec![1.0]];
+    for (_, weights, _) in tallies.iter() {
+        before.push(convolve(before.last().unwrap(), weights));
+    }
+    let mut after = vec![vec![1.0]];
+    for (_, weights, _) in tallies.iter().rev() {
+        after.push(convolve(after.last().unwrap(), weights));
+    }
+    after.reverse();
+
+    // Ways the loose tiles' slots can take the mines the numbered area leaves, in log
+    // form and shifted so the largest is 1, since they can be far too big for f64
+    let slots = loose.len() * max_per_tile.max(0) as usize;
+    let ln_factorials = ln_factorials(slots);
+    let ln_loose_ways = |total: usize| -> Option<f64> {
+        let left = mine_count as i64 - total as i64;
+        if left < 0 || left > slots as i64 {
+            return None;
+        }
+        let left = left as usize;
+        Some(ln_factorials[slots] - ln_factorials[left] - ln_factorials[slots - left])
+    };
+    let all = before.last().unwrap();
+    let shift = (0..all.len())
+        .filter(|&t| all[t] > 0.0)
+        .filter_map(|t| ln_loose_ways(t).map(|w| w + all[t].ln()))
+        .fold(f64::NEG_INFINITY, f64::max);
+    let loose_ways = |total: usize| ln_loose_ways(total).map_or(0.0, |w| (w - shift).exp());
+
+    let everything: f64 = (0..all.len()).map(|t| all[t] * loose_ways(t)).sum();
+    let mut chances = Vec::new();
+    if everything == 0.0 {
+        return chances;
+    }
+
+    for (n, (group, _, mine_weights)) in tallies.iter().enumerate() {
+        let rest = convolve(&before[n], &after[n + 1]);
+        for (k, &cell) in group.iter().enumerate() {
+            let mut with_mine = 0.0;
+            for (total, &w) in mine_weights[k]
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| **w > 0.0)
+            {
+                for (other, &r) in rest.iter().enumerate() {
+                    with_mine += w * r * loose_ways(total + other);
+                }
+            }
+            chances.push((cells[cell], with_mine / everything));
+        }
+    }
+
+    // A loose tile is safe when none of the mines left for the loose tiles land in its
+    // slots
+    if !loose.is_empty() {
+        let per_tile = max_per_tile as usize;
+        let mut with_mine = 0.0;
+        for (total, &w) in all.iter().enumerate().filter(|(_, w)| **w > 0.0) {
+            let left = mine_count as i64 - total as i64;
+            if left < 0 || left > (slots - per_tile) as i64 {
+                with_mine += w * loose_ways(total);
+                continue;
+            }
+            let left = left as usize;
+            let ln_empty = ln_factorials[slots - per_tile]
+                - ln_factorials[left]
+                - ln_factorials[slots - per_tile - left];
+            let ln_all = ln_loose_ways(total).unwrap_or(f64::NEG_INFINITY);
+            with_mine += w * loose_ways(total) * (1.0 - (ln_empty - ln_all).exp());
+        }
+        let chance = with_mine / everything;
+        chances.extend(loose.into_iter().map(|p| (p, chance)));
+    }
+
+    chances
+}
+
+// Weights of the sum of two independent totals
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut sums = vec![0.0; a.len() + b.len() - 1];
+    for (i, &x) in a.iter().enumerate().filter(|(_, x)| **x > 0.0) {
+        for (j, &y) in b.iter().enumerate() {
+            sums[i + j] += x * y;
+        }
+    }
+    sums
+}
+
+// ln(n!) for n up to max
+fn ln_factorials(max: usize) -> Vec<f64> {
+    let mut table = vec![0.0; max + 1];
+    for n in 1..=max {
+        table[n] = table[n - 1] + (n as f64).ln();
+    }
+    table
+}
+
+fn ln_choose(n: usize, k: usize) -> f64 {
+    (0..k.min(n - k))
+        .map(|i| ((n - i) as f64 / (i + 1) as f64).ln())
+        .sum()
+}
+
+// The hidden tiles next to a revealed number, their index in that list, and what each
+// number says about them
+struct Frontier {
+    cells: Vec<[i32; 2]>,
+    index: HashMap<[i32; 2], usize>,
+    constraints: Vec<Constraint>,
+}
+
+fn read_constraints(
+    board: &VisibleBoard,
+    topology: &dyn Topology,
+    clue_rule: ClueRule,
+) -> Frontier {
+    let mut cells: Vec<[i32; 2]> = Vec::new();
+    let mut index: HashMap<[i32; 2], usize> = HashMap::new();
+    let mut constraints: Vec<Constraint> = Vec::new();
+
+    for [x, y] in topology.positions() {
+        if !board.is_revealed(x, y) {
+            continue;
+        }
+
+        let mut inline = Vec::new();
+        let mut others = Vec::new();
+        for [nx, ny] in topology.neighbours(x, y) {
+            if board.is_revealed(nx, ny) {
+                continue;
+            }
+            let cell = *index.entry([nx, ny]).or_insert_with(|| {
+                cells.push([nx, ny]);
+                cells.len() - 1
+            });
+            if nx == x || ny == y {
+                inline.push(cell);
+            } else {
+                others.push(cell);
+            }
+        }
+
+        let num = board.get_num(x, y);
+        match clue_rule {
+            ClueRule::Standard => {
+                inline.append(&mut others);
+                constraints.push(Constraint {
+                    cells: inline,
+                    counts: vec![num],
+                });
+            }
+            ClueRule::Liar => {
+                inline.append(&mut others);
+                constraints.push(Constraint {
+                    cells: inline,
+                    counts: vec![num - 1, num + 1],
+                });
+            }
+            ClueRule::ColourSplit => {
+                constraints.push(Constraint {
+                    cells: inline,
+                    counts: vec![board.get_split(x, y)],
+                });
+                constraints.push(Constraint {
+                    cells: others,
+                    counts: vec![num - board.get_split(x, y)],
+                });
+            }
+        }
+    }
+
+    Frontier {
+        cells,
+        index,
+        constraints,
+    }
+}
+
+// The constraints each cell appears in
+fn watching(cell_count: usize, constraints: &[Constraint]) -> Vec<Vec<usize>> {
+    let mut watching: Vec<Vec<usize>> = vec![Vec::new(); cell_count];
+    for (n, constraint) in constraints.iter().enumerate() {
+        for &cell in constraint.cells.iter() {
+            watching[cell].push(n);
+        }
+    }
+    watching
+}
+
+// Splits the cells into groups that share no constraint, so each can be searched alone
+fn groups(cell_count: usize, constraints: &[Constraint]) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..cell_count).collect();
+    fn root(parent: &mut [usize], mut cell: usize) -> usize {
+        while parent[cell] != cell {
+            parent[cell] = parent[parent[cell]];
+            cell = parent[cell];
+        }
+        cell
+    }
+
+    for constraint in constraints.iter() {
+        for pair in constraint.cells.windows(2) {
+            let (a, b) = (root(&mut parent, pair[0]), root(&mut parent, pair[1]));
+            parent[a] = b;
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for cell in 0..cell_count {
+        let r = root(&mut parent, cell);
+        groups.entry(r).or_default().push(cell);
+    }
+    groups.into_values().collect()
+}
+
+// Backtracking over the mines on each cell of one group
+struct Search<'a> {
+    group: &'a [usize],
+    constraints: &'a [Constraint],
+    watching: &'a [Vec<usize>],
+    max_per_tile: i32,
+    // Mines on each cell, -1 while unassigned
+    values: Vec<i32>,
+    // Fewest and most mines each cell may be given
+    range: Vec<(i32, i32)>,
+    // Stop at the first layout found rather than visiting them all
+    first_only: bool,
+    can_be_safe: Vec<bool>,
+    can_be_mine: Vec<bool>,
+    least: i32,
+    most: i32,
+    // Steps left before giving up
+    steps: usize,
+    // With weigh set, the summed weight of the layouts found for each number of mines
+    // in the group, overall and for those with a mine on group[k]
+    weigh: bool,
+    weights: Vec<f64>,
+    mine_weights: Vec<Vec<f64>>,
+    // Ways of putting v mines in one tile's max_per_tile slots, by v
+    slot_ways: Vec<f64>,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        group: &'a [usize],
+        constraints: &'a [Constraint],
+        watching: &'a [Vec<usize>],
+        max_per_tile: i32,
+        weigh: bool,
+    ) -> Self {
+        let cell_count = watching.len();
+        let totals = if weigh {
+            group.len() * max_per_tile as usize + 1
+        } else {
+            0
+        };
+        Search {
+            group,
+            constraints,
+            watching,
+            max_per_tile,
+            values: vec![-1; cell_count],
+            range: vec![(0, max_per_tile); cell_count],
+            first_only: false,
+            can_be_safe: vec![false; cell_count],
+            can_be_mine: vec![false; cell_count],
+            least: i32::MAX,
+            most: 0,
+            steps: LIST_LIMIT,
+            weigh,
+            weights: vec![0.0; totals],
+            mine_weights: vec![vec![0.0; totals]; if weigh { group.len() } else { 0 }],
+            slot_ways: (0..=max_per_tile)
+                .map(|v| ln_choose(max_per_tile as usize, v as usize).exp())
+                .collect(),
+        }
+    }
+
+    // Visits the layouts of group[k..]. Returns None if it ran out of steps, otherwise
+    // whether it stopped at a layout
+    fn run(&mut self, k: usize) -> Option<bool> {
+        if self.steps == 0 {
+            return None;
+        }
+        self.steps -= 1;
+
+        if k == self.group.len() {
+            let mut total = 0;
+            for &cell in self.group.iter() {
+                total += self.values[cell];
+                if self.values[cell] == 0 {
+                    self.can_be_safe[cell] = true;
+                } else {
+                    self.can_be_mine[cell] = true;
+                }
+            }
+            self.least = self.least.min(total);
+            self.most = self.most.max(total);
+            if self.weigh {
+                // Mines land in distinct slots, so a layout is as likely as the ways its
+                // mines fit in their tiles' slots
+                let weight: f64 = self
+                    .group
+                    .iter()
+                    .map(|&cell| self.slot_ways[self.values[cell] as usize])
+                    .product();
+                self.weights[total as usize] += weight;
+                for (k, &cell) in self.group.iter().enumerate() {
+                    if self.values[cell] > 0 {
+                        self.mine_weights[k][total as usize] += weight;
+                    }
+                }
+            }
+            return Some(self.first_only);
+        }
+
+        let cell = self.group[k];
+        let (low, high) = self.range[cell];
+        let mut result = Some(false);
+        for value in low..=high {
+            self.values[cell] = value;
+            if self.watching[cell]
+                .iter()
+                .all(|&c| self.possible(&self.constraints[c]))
+            {
+                result = self.run(k + 1);
+                if result != Some(false) {
+                    break;
+                }
+            }
+        }
+        self.values[cell] = -1;
+        result
+    }
+
+    // Looks for one layout where cell holds a number of mines within range
+    fn ask(&mut self, cell: usize, range: (i32, i32)) -> Option<bool> {
+        let full = self.range[cell];
+        self.range[cell] = range;
+        self.steps = QUERY_LIMIT;
+        let found = self.run(0);
+        self.range[cell] = full;
+        found
+    }
+
+    // Whether the cells still to be assigned can bring the constraint to one of its counts
+    fn possible(&self, constraint: &Constraint) -> bool {
+        let mut placed = 0;
+        let mut open = 0;
+        for &cell in constraint.cells.iter() {
+            if self.values[cell] < 0 {
+                open += 1;
+            } else {
+                placed += self.values[cell];
+            }
+        }
+        constraint
+            .counts
+            .iter()
+            .any(|&count| placed <= count && count <= placed + open * self.max_per_tile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topology::SquareGrid;
+    use crate::visible::VisibleTile;
+
+    // A board from rows of '.' for hidden tiles and digits for revealed numbers
+    fn read_board(rows: &[&str]) -> (VisibleBoard, SquareGrid) {
+        let (width, height) = (rows[0].len() as i32, rows.len() as i32);
+        let mut board = VisibleBoard::hidden(width, height);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if let Some(num) = c.to_digit(10) {
+                    board.set_tile(x as i32, y as i32, VisibleTile::Revealed(num as i32));
+                }
+            }
+        }
+        (board, SquareGrid::new(width, height))
+    }
+
+    fn sorted(mut tiles: Vec<[i32; 2]>) -> Vec<[i32; 2]> {
+        tiles.sort();
+        tiles
+    }
+
+    fn chance(chances: &[([i32; 2], f64)], position: [i32; 2]) -> f64 {
+        chances.iter().find(|(p, _)| *p == position).unwrap().1
+    }
+
+    #[test]
+    fn one_one_against_the_edge() {
+        // The mine under the first 1 is also under the second, so the third tile above
+        // and the tile beside the second 1 are safe
+        let (board, topology) = read_board(&["....", "11.."]);
+        let deductions = deduce(&board, &topology, ClueRule::Standard, 1, 2);
+        assert_eq!(sorted(deductions.safe), vec![[2, 0], [2, 1]]);
+        assert!(deductions.mines.is_empty());
+    }
+
+    #[test]
+    fn one_two_one() {
+        let (board, topology) = read_board(&["...", "121"]);
+        let deductions = deduce(&board, &topology, ClueRule::Standard, 1, 2);
+        assert_eq!(deductions.safe, vec![[1, 0]]);
+        assert_eq!(sorted(deductions.mines), vec![[0, 0], [2, 0]]);
+    }
+
+    #[test]
+    fn loose_tiles_settled_by_the_mine_count() {
+        let (board, topology) = read_board(&["...", "121", "000"]);
+        let deductions = deduce(&board, &topology, ClueRule::Standard, 1, 2);
+        assert_eq!(deductions.safe, vec![[1, 0]]);
+
+        let (board, topology) = read_board(&["....", "11.."]);
+        let deductions = deduce(&board, &topology, ClueRule::Standard, 1, 3);
+        assert_eq!(sorted(deductions.mines), vec![[3, 0], [3, 1]]);
+    }
+
+    #[test]
+    fn probabilities_add_up_to_the_mine_count() {
+        for rows in [&["....", "11.."][..], &["....", ".1..", "...."][..]] {
+            let (board, topology) = read_board(rows);
+            let chances = probabilities(&board, &topology, ClueRule::Standard, 1, 2);
+            let total: f64 = chances.iter().map(|(_, c)| c).sum();
+            assert!((total - 2.0).abs() < 1e-9, "{} for {:?}", total, rows);
+        }
+
+        let (board, topology) = read_board(&["....", "11.."]);
+        let chances = probabilities(&board, &topology, ClueRule::Standard, 1, 2);
+        assert_eq!(chance(&chances, [2, 0]), 0.0);
+        assert!((chance(&chances, [0, 0]) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn liar_numbers_are_one_off() {
+        // A 2 with one hidden neighbour really means 1, and a 1 really means 0
+        let (board, topology) = read_board(&["2."]);
+        let deductions = deduce(&board, &topology, ClueRule::Liar, 1, 1);
+        assert_eq!(deductions.mines, vec![[1, 0]]);
+
+        let (board, topology) = read_board(&["1."]);
+        let deductions = deduce(&board, &topology, ClueRule::Liar, 1, 0);
+        assert_eq!(deductions.safe, vec![[1, 0]]);
+
+        // Read as the truth, the 1 would prove nothing on its own
+        let (board, topology) = read_board(&["...", ".1.", "..."]);
+        let deductions = deduce(&board, &topology, ClueRule::Liar, 1, 2);
+        assert!(deductions.safe.is_empty() && deductions.mines.is_empty());
+        let chances = probabilities(&board, &topology, ClueRule::Liar, 1, 2);
+        let total: f64 = chances.iter().map(|(_, c)| c).sum();
+        assert!((total - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn colour_split_separates_inline_mines() {
+        // The one mine around the centre is on a diagonal, so its row and column are safe
+        let (mut board, topology) = read_board(&["...", ".1.", "..."]);
+        board.set_split(1, 1, 0);
+        let deductions = deduce(&board, &topology, ClueRule::ColourSplit, 1, 1);
+        assert_eq!(
+            sorted(deductions.safe),
+            vec![[0, 1], [1, 0], [1, 2], [2, 1]]
+        );
+        assert!(deductions.mines.is_empty());
+
+        board.set_split(1, 1, 1);
+        let deductions = deduce(&board, &topology, ClueRule::ColourSplit, 1, 1);
+        assert_eq!(
+            sorted(deductions.safe),
+            vec![[0, 0], [0, 2], [2, 0], [2, 2]]
+        );
+    }
+
+    #[test]
+    fn several_mines_per_tile() {
+        // One tile can hold both mines of a 2
+        let (board, topology) = read_board(&["2."]);
+        let deductions = deduce(&board, &topology, ClueRule::Standard, 2, 2);
+        assert_eq!(deductions.mines, vec![[1, 0]]);
+
+        // Split 1 and 1 the mines fit 2 x 2 ways, against 1 way each for both on one
+        // side, so a side holds a mine in 5 of 6 equally likely placements
+        let (board, topology) = read_board(&[".2."]);
+        let deductions = deduce(&board, &topology, ClueRule::Standard, 2, 2);
+        assert!(deductions.safe.is_empty() && deductions.mines.is_empty());
+        let chances = probabilities(&board, &topology, ClueRule::Standard, 2, 2);
+        assert!((chance(&chances, [0, 0]) - 5.0 / 6.0).abs() < 1e-9);
+        assert!((chance(&chances, [2, 0]) - 5.0 / 6.0).abs() < 1e-9);
+    }
+}
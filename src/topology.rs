@@ -0,0 +1,437 @@
+// How the tiles of a board are laid out and which tiles touch. The game rules in
+// minesweeper.rs only ever ask a topology for neighbours and positions, so a new
+// board type only needs a new implementation here.
+pub trait Topology: Send + Sync {
+    // Width and height of the board array
+    fn dimensions(&self) -> [i32; 2];
+
+    // Positions around (x, y) that are on the board, each listed once
+    fn neighbours(&self, x: i32, y: i32) -> Vec<[i32; 2]>;
+
+    // How the GUI should draw and hit-test tiles
+    fn tile_shape(&self) -> TileShape {
+        TileShape::Square
+    }
+
+    // Whether opposite edges of the board touch
+    fn wraps(&self) -> bool {
+        false
+    }
+
+    // Number of layers stacked in the board array, each dimensions()[1] / layers() rows
+    fn layers(&self) -> i32 {
+        1
+    }
+
+    // Whether (x, y) is a tile of this board
+    fn contains(&self, x: i32, y: i32) -> bool {
+        let [width, height] = self.dimensions();
+        x >= 0 && y >= 0 && x < width && y < height
+    }
+
+    // Every tile of the board, row by row
+    fn positions(&self) -> Vec<[i32; 2]> {
+        let [width, height] = self.dimensions();
+        let mut positions = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                if self.contains(x, y) {
+                    positions.push([x, y]);
+                }
+            }
+        }
+        positions
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum TileShape {
+    Square,
+    // Pointy-top hexes, with odd rows shifted half a tile to the right
+    Hex,
+}
+
+const SQUARE_OFFSETS: [[i32; 2]; 8] = [
+    [-1, -1],
+    [0, -1],
+    [1, -1],
+    [-1, 0],
+    [1, 0],
+    [-1, 1],
+    [0, 1],
+    [1, 1],
+];
+const HEX_EVEN_ROW_OFFSETS: [[i32; 2]; 6] = [[-1, -1], [0, -1], [-1, 0], [1, 0], [-1, 1], [0, 1]];
+const HEX_ODD_ROW_OFFSETS: [[i32; 2]; 6] = [[0, -1], [1, -1], [-1, 0], [1, 0], [0, 1], [1, 1]];
+
+// Applies offsets to (x, y), dropping anything off the board
+pub fn offset_neighbours(
+    topology: &dyn Topology,
+    offsets: &[[i32; 2]],
+    x: i32,
+    y: i32,
+) -> Vec<[i32; 2]> {
+    offsets
+        .iter()
+        .map(|[dx, dy]| [x + dx, y + dy])
+        .filter(|[nx, ny]| topology.contains(*nx, *ny))
+        .collect()
+}
+
+// The classic board, eight neighbours except along the edges
+pub struct SquareGrid {
+    width: i32,
+    height: i32,
+}
+
+impl SquareGrid {
+    pub fn new(width: i32, height: i32) -> Self {
+        SquareGrid { width, height }
+    }
+}
+
+impl Topology for SquareGrid {
+    fn dimensions(&self) -> [i32; 2] {
+        [self.width, self.height]
+    }
+
+    fn neighbours(&self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        offset_neighbours(self, &SQUARE_OFFSETS, x, y)
+    }
+}
+
+const KNIGHT_OFFSETS: [[i32; 2]; 8] = [
+    [-1, -2],
+    [1, -2],
+    [-2, -1],
+    [2, -1],
+    [-2, 1],
+    [2, 1],
+    [-1, 2],
+    [1, 2],
+];
+
+// A square board where tiles touch the eight tiles a chess knight could move to
+pub struct KnightGrid {
+    width: i32,
+    height: i32,
+}
+
+impl KnightGrid {
+    pub fn new(width: i32, height: i32) -> Self {
+        KnightGrid { width, height }
+    }
+}
+
+impl Topology for KnightGrid {
+    fn dimensions(&self) -> [i32; 2] {
+        [self.width, self.height]
+    }
+
+    fn neighbours(&self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        offset_neighbours(self, &KNIGHT_OFFSETS, x, y)
+    }
+}
+
+// A square board whose opposite edges touch, so every tile has eight neighbours
+pub struct WrapGrid {
+    width: i32,
+    height: i32,
+}
+
+impl WrapGrid {
+    // Panics on an empty board, which has nothing to wrap around to
+    pub fn new(width: i32, height: i32) -> Self {
+        assert!(
+            width > 0 && height > 0,
+            "Invalid wrapping board size {}x{}, it needs at least one tile",
+            width,
+            height
+        );
+        WrapGrid { width, height }
+    }
+}
+
+impl Topology for WrapGrid {
+    fn dimensions(&self) -> [i32; 2] {
+        [self.width, self.height]
+    }
+
+    fn neighbours(&self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        let mut neighbours: Vec<[i32; 2]> = Vec::new();
+        for [dx, dy] in SQUARE_OFFSETS {
+            let nx = (x + dx).rem_euclid(self.width);
+            let ny = (y + dy).rem_euclid(self.height);
+            // Boards under three tiles across would otherwise list a tile twice
+            if [nx, ny] != [x, y] && !neighbours.contains(&[nx, ny]) {
+                neighbours.push([nx, ny]);
+            }
+        }
+        neighbours
+    }
+
+    fn wraps(&self) -> bool {
+        true
+    }
+}
+
+// Hex tiles stored in odd-row-offset coordinates, six neighbours each
+pub struct HexGrid {
+    width: i32,
+    height: i32,
+}
+
+impl HexGrid {
+    pub fn new(width: i32, height: i32) -> Self {
+        HexGrid { width, height }
+    }
+}
+
+impl Topology for HexGrid {
+    fn dimensions(&self) -> [i32; 2] {
+        [self.width, self.height]
+    }
+
+    fn neighbours(&self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        if y % 2 == 0 {
+            offset_neighbours(self, &HEX_EVEN_ROW_OFFSETS, x, y)
+        } else {
+            offset_neighbours(self, &HEX_ODD_ROW_OFFSETS, x, y)
+        }
+    }
+
+    fn tile_shape(&self) -> TileShape {
+        TileShape::Hex
+    }
+}
+
+// A width x height x depth block of cubes, each touching the 26 around it. Layer z
+// is stored as rows z * height to (z + 1) * height - 1 of the board array
+pub struct CubeGrid {
+    width: i32,
+    height: i32,
+    depth: i32,
+}
+
+impl CubeGrid {
+    pub fn new(width: i32, height: i32, depth: i32) -> Self {
+        CubeGrid {
+            width,
+            height,
+            depth,
+        }
+    }
+}
+
+impl Topology for CubeGrid {
+    fn dimensions(&self) -> [i32; 2] {
+        [self.width, self.height * self.depth]
+    }
+
+    fn neighbours(&self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        let (z, layer_y) = (y / self.height, y % self.height);
+        let mut neighbours = Vec::new();
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nx, ny, nz) = (x + dx, layer_y + dy, z + dz);
+                    if [dx, dy, dz] != [0, 0, 0]
+                        && (0..self.width).contains(&nx)
+                        && (0..self.height).contains(&ny)
+                        && (0..self.depth).contains(&nz)
+                    {
+                        neighbours.push([nx, nz * self.height + ny]);
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    fn layers(&self) -> i32 {
+        self.depth
+    }
+}
+
+// Another board with some of its tiles cut out. Cut out tiles don't exist: they're
+// never anyone's neighbour, never drawn and never clicked
+pub struct MaskedGrid {
+    base: Box<dyn Topology>,
+    // mask[y][x] is true for tiles that are part of the board
+    mask: Vec<Vec<bool>>,
+}
+
+impl MaskedGrid {
+    pub fn new(base: Box<dyn Topology>, mask: Vec<Vec<bool>>) -> Self {
+        MaskedGrid { base, mask }
+    }
+}
+
+impl Topology for MaskedGrid {
+    fn dimensions(&self) -> [i32; 2] {
+        self.base.dimensions()
+    }
+
+    fn neighbours(&self, x: i32, y: i32) -> Vec<[i32; 2]> {
+        self.base
+            .neighbours(x, y)
+            .into_iter()
+            .filter(|[nx, ny]| self.contains(*nx, *ny))
+            .collect()
+    }
+
+    fn tile_shape(&self) -> TileShape {
+        self.base.tile_shape()
+    }
+
+    fn wraps(&self) -> bool {
+        self.base.wraps()
+    }
+
+    fn layers(&self) -> i32 {
+        self.base.layers()
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        self.base.contains(x, y)
+            && self
+                .mask
+                .get(y as usize)
+                .and_then(|row| row.get(x as usize))
+                .copied()
+                .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut positions: Vec<[i32; 2]>) -> Vec<[i32; 2]> {
+        positions.sort_by_key(|[x, y]| [*y, *x]);
+        positions
+    }
+
+    // Every tile is a neighbour of its neighbours, on the board and not itself
+    fn assert_symmetric(topology: &dyn Topology) {
+        for [x, y] in topology.positions() {
+            for [nx, ny] in topology.neighbours(x, y) {
+                assert!(topology.contains(nx, ny) && [nx, ny] != [x, y]);
+                assert!(topology.neighbours(nx, ny).contains(&[x, y]));
+            }
+        }
+    }
+
+    #[test]
+    fn hex_neighbours() {
+        let topology = HexGrid::new(5, 5);
+        assert_eq!(
+            sorted(topology.neighbours(2, 2)),
+            vec![[1, 1], [2, 1], [1, 2], [3, 2], [1, 3], [2, 3]]
+        );
+        // Odd rows sit half a tile right, so their diagonals lean right
+        assert_eq!(
+            sorted(topology.neighbours(2, 1)),
+            vec![[2, 0], [3, 0], [1, 1], [3, 1], [2, 2], [3, 2]]
+        );
+        // Even rows stick out on the left edge and odd rows on the right
+        assert_eq!(topology.neighbours(0, 2).len(), 3);
+        assert_eq!(topology.neighbours(0, 1).len(), 5);
+        assert_eq!(topology.neighbours(4, 1).len(), 3);
+        assert_eq!(topology.neighbours(4, 2).len(), 5);
+        assert_symmetric(&topology);
+    }
+
+    #[test]
+    fn wrap_neighbours() {
+        let topology = WrapGrid::new(4, 4);
+        assert!(topology
+            .positions()
+            .iter()
+            .all(|[x, y]| topology.neighbours(*x, *y).len() == 8));
+        assert_eq!(
+            sorted(topology.neighbours(0, 0)),
+            sorted(vec![
+                [1, 0],
+                [3, 0],
+                [0, 1],
+                [1, 1],
+                [3, 1],
+                [0, 3],
+                [1, 3],
+                [3, 3]
+            ])
+        );
+        assert_symmetric(&topology);
+    }
+
+    #[test]
+    fn small_wraps_list_each_neighbour_once() {
+        assert!(WrapGrid::new(1, 1).neighbours(0, 0).is_empty());
+        assert_eq!(
+            sorted(WrapGrid::new(2, 2).neighbours(0, 0)),
+            vec![[1, 0], [0, 1], [1, 1]]
+        );
+        assert_eq!(
+            sorted(WrapGrid::new(1, 3).neighbours(0, 1)),
+            vec![[0, 0], [0, 2]]
+        );
+        assert_eq!(WrapGrid::new(3, 3).neighbours(1, 1).len(), 8);
+        assert_symmetric(&WrapGrid::new(2, 2));
+    }
+
+    #[test]
+    fn cube_neighbours() {
+        let topology = CubeGrid::new(3, 3, 3);
+        assert_eq!(topology.dimensions(), [3, 9]);
+        // The middle cube of the middle layer, and a corner of the bottom layer
+        assert_eq!(topology.neighbours(1, 4).len(), 26);
+        assert_eq!(
+            sorted(topology.neighbours(0, 0)),
+            vec![[1, 0], [0, 1], [1, 1], [0, 3], [1, 3], [0, 4], [1, 4]]
+        );
+        // The middle of a face of the block
+        assert_eq!(topology.neighbours(1, 1).len(), 17);
+        // A block one layer deep is a square board
+        assert_eq!(CubeGrid::new(3, 3, 1).neighbours(1, 1).len(), 8);
+        assert_symmetric(&topology);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid wrapping board size 0x3")]
+    fn empty_wrap_is_rejected() {
+        WrapGrid::new(0, 3);
+    }
+
+    #[test]
+    fn masked_tiles_are_nobodys_neighbour() {
+        // A ring: the middle of a 3x3 board cut out
+        let mut mask = vec![vec![true; 3]; 3];
+        mask[1][1] = false;
+        let topology = MaskedGrid::new(Box::new(SquareGrid::new(3, 3)), mask);
+
+        assert!(!topology.contains(1, 1));
+        assert_eq!(topology.positions().len(), 8);
+        assert_eq!(sorted(topology.neighbours(0, 0)), vec![[1, 0], [0, 1]]);
+        assert_eq!(topology.neighbours(1, 0).len(), 4);
+        assert!(topology
+            .positions()
+            .iter()
+            .all(|[x, y]| !topology.neighbours(*x, *y).contains(&[1, 1])));
+        assert_symmetric(&topology);
+    }
+
+    #[test]
+    fn masks_keep_the_base_shape() {
+        // Short mask rows cut off the rest of the row
+        let mask = vec![vec![true; 4], vec![true; 2]];
+        let topology = MaskedGrid::new(Box::new(HexGrid::new(4, 2)), mask);
+        assert!(topology.tile_shape() == TileShape::Hex);
+        assert!(!topology.contains(2, 1));
+        assert_eq!(
+            sorted(topology.neighbours(1, 1)),
+            vec![[1, 0], [2, 0], [0, 1]]
+        );
+        assert_symmetric(&topology);
+    }
+}
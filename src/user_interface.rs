@@ -3,8 +3,11 @@ extern crate graphics;
 extern crate opengl_graphics;
 extern crate piston;
 
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use graphics::glyph_cache::rusttype::GlyphCache as Cache;
 use graphics::rectangle::Border;
@@ -14,45 +17,167 @@ use opengl_graphics::{GlGraphics, GlyphCache, OpenGL, Texture, TextureSettings};
 
 use piston::event_loop::{EventSettings, Events};
 use piston::input::{Button, ButtonEvent, ButtonState, MouseButton, MouseCursorEvent, RenderEvent};
-use piston::window::WindowSettings;
+use piston::window::{AdvancedWindow, WindowSettings};
 
 use glutin_window::GlutinWindow as Window;
 use piston::{ButtonArgs, Key, RenderArgs, UpdateEvent};
 
+use crate::autosolver::Autosolver;
+use crate::daily;
+use crate::daily::DailyResult;
 use crate::minesweeper;
-use crate::minesweeper::MinesweeperBoard;
+use crate::minesweeper::{ClueRule, MinesweeperBoard};
+use crate::multiplayer::{Player, Session, Snapshot};
+use crate::placement::Placement;
+use crate::puzzle;
+use crate::puzzle::{Goal, PuzzlePack};
+use crate::review;
+use crate::review::{Finding, Review, Verdict};
+use crate::simulation::{Action, Move};
+use crate::solver;
+use crate::topology::{KnightGrid, SquareGrid, TileShape, Topology};
+use crate::visible::{VisibleBoard, VisibleTile};
 
 const PIXEL_SIZE: f64 = 32.0;
+// Centre to corner of a pointy-top hex that is PIXEL_SIZE wide
+const HEX_RADIUS: f64 = PIXEL_SIZE / 1.732_050_807_568_877_2;
+const GHOST_ALPHA: f32 = 0.35;
+// A cascade opens one tile every REVEAL_STEP seconds, sped up to finish within
+// REVEAL_ANIMATION
+const REVEAL_STEP: f64 = 0.004;
+const REVEAL_ANIMATION: f64 = 0.3;
+const WRAP_EDGE_COLOR: [f32; 4] = [0.2, 0.45, 0.85, 0.6];
+// How far a hovered tile is lightened towards white
+const HOVER_LIGHTEN: f32 = 0.3;
+// Outline of the first mistake a review finds on a lost board
+const REVIEW_ERROR_COLOR: [f32; 4] = [0.0, 0.45, 1.0, 1.0];
 const FONT: &[u8] = include_bytes!("mine-sweeper.ttf");
+const PLAYER_COLORS: [[f32; 4]; 6] = [
+    color::BLUE,
+    color::RED,
+    color::PURPLE,
+    color::TEAL,
+    color::OLIVE,
+    color::MAGENTA,
+];
+// Rule sets offered by the new game menu, picked with the number keys
+const MENU_RULES: [&str; 4] = ["STANDARD", "KNIGHT MOVES", "LIAR", "COLOUR SPLIT"];
+// Smallest window for the level select list, in tiles
+const LEVEL_SELECT_WIDTH: i32 = 14;
+// Seconds the built-in player leaves a finished board up before starting another, and
+// its slowest and fastest speeds in moves per second
+const WATCH_PAUSE: f64 = 2.0;
+const WATCH_SPEED: f64 = 4.0;
+const WATCH_SPEEDS: (f64, f64) = (0.25, 64.0);
 
+// Everything a move can change, kept for undo and redo
+#[derive(Clone)]
+struct BoardState {
+    board: MinesweeperBoard,
+    mine_count: i32,
+    game_result: i32,
+    hit_mine: [i32; 2],
+    // The tile clicked to reach this state
+    last_click: Option<[i32; 2]>,
+}
+
+// A pack of hand made puzzles, played one at a time from a level select list
+struct PuzzleMode {
+    pack: PuzzlePack,
+    progress_path: PathBuf,
+    solved: Vec<String>,
+    // The puzzle being played, or highlighted in the list
+    current: usize,
+    choosing: bool,
+    // Lost a no guessing puzzle by clicking a tile that wasn't proven safe
+    guessed: bool,
+}
+
+// The built-in player taking the moves
+struct Autoplay {
+    last_move: Instant,
+    // Moves already worked out, made one per tick
+    queue: VecDeque<Move>,
+}
+
+// The board everyone plays on a given date
+struct DailyGame {
+    date: String,
+    seed: u64,
+    // Only the day's first attempt is recorded
+    first_attempt: bool,
+}
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct GUI<'a> {
     board: MinesweeperBoard,
     start_mine_count: i32,
     current_mine_count: i32,
     height: i32,
     width: i32,
+    topology: Box<dyn Topology>,
+    // 3D boards show one layer of view_height rows at a time
+    layer: i32,
+    view_height: i32,
     tiles_to_win: i32,
     world_size: [f64; 2],
+    ghosts: bool,
+    board_offset: [f64; 2],
     game_result: i32,
     hit_mine: [i32; 2],
+    last_click: Option<[i32; 2]>,
+    // What the solver makes of the click that lost the game
+    fatal: Option<Finding>,
+    // The whole lost game looked over, worked out when V first asks for it and shown
+    // while review_open
+    review: Option<Review>,
+    review_open: bool,
     mouse_pos: [i32; 2],
+    // The left button went down on the board, and opens a tile when it comes back up
+    left_held: bool,
+    session: Option<Session>,
+    // The board as the server last sent it, in place of board in a shared game
+    shared_view: Option<VisibleBoard>,
+    players: Vec<Player>,
+    lives: Option<i32>,
+    undo_stack: Vec<BoardState>,
+    redo_stack: Vec<BoardState>,
+    practice: bool,
+    ranked: bool,
+    question_marks: bool,
+    max_per_tile: i32,
+    clue_rule: ClueRule,
+    placement: Placement,
+    // The board picked at launch, set aside while the menu has swapped in knight moves
+    launch_topology: Option<Box<dyn Topology>>,
+    menu_open: bool,
+    puzzle_mode: Option<PuzzleMode>,
+    daily: Option<DailyGame>,
+    autoplay: Option<Autoplay>,
+    // Moves per second the built-in player makes
+    watch_speed: f64,
+    // Set by the first reveal, and the time taken once the game ends
+    started: Option<Instant>,
+    finished: Option<f64>,
+    // Tiles from the last cascade still waiting to be drawn open, with the seconds after
+    // reveal_start they appear at
+    reveal_delays: HashMap<[i32; 2], f64>,
+    reveal_start: Instant,
     cache: Cache<'a, (), Texture>,
     window: Window,
     gl: GlGraphics,
 }
 
 impl<'a> GUI<'a> {
-    pub fn new(board: MinesweeperBoard, mine_count: i32) -> Self {
+    pub fn new(board: MinesweeperBoard, mine_count: i32, topology: Box<dyn Topology>) -> Self {
         // Board information
-        let height = board.len() as i32;
-        let width = board[0].len() as i32;
-        let tiles_to_win = (height * width) - mine_count;
+        let height = board.height();
+        let width = board.width();
+        let tiles_to_win = safe_tiles(&board, topology.as_ref());
 
         // Graphical info
-        let world_size = [
-            (PIXEL_SIZE * width as f64),
-            (PIXEL_SIZE * height as f64) + (PIXEL_SIZE * 2.0),
-        ];
+        let view_height = height / topology.layers();
+        let world_size = world_size(topology.tile_shape(), width, view_height, false);
 
         let opengl = OpenGL::V3_2;
         let window: Window = WindowSettings::new("Minesweeper", world_size)
@@ -74,92 +199,886 @@ impl<'a> GUI<'a> {
             current_mine_count: mine_count,
             height,
             width,
+            topology,
+            layer: 0,
+            view_height,
             tiles_to_win,
             world_size,
+            ghosts: false,
+            board_offset: [0.0, 0.0],
             game_result: 0,
             hit_mine: [-1, -1],
+            last_click: None,
+            fatal: None,
+            review: None,
+            review_open: false,
             mouse_pos: [0, 0],
+            left_held: false,
+            session: None,
+            shared_view: None,
+            players: Vec::new(),
+            lives: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            practice: false,
+            ranked: true,
+            question_marks: true,
+            max_per_tile: 1,
+            clue_rule: ClueRule::Standard,
+            placement: Placement::default(),
+            launch_topology: None,
+            menu_open: false,
+            puzzle_mode: None,
+            daily: None,
+            autoplay: None,
+            watch_speed: WATCH_SPEED,
+            started: None,
+            reveal_delays: HashMap::new(),
+            reveal_start: Instant::now(),
+            finished: None,
             cache,
             window,
             gl,
         }
     }
 
+    // Builds a window for a shared game where every move goes through the server
+    pub fn join(session: Session, snapshot: Snapshot) -> Self {
+        let mut gui = GUI::new(
+            MinesweeperBoard::new(snapshot.board.width(), snapshot.board.height()),
+            snapshot.mine_count,
+            Box::new(SquareGrid::new(
+                snapshot.board.width(),
+                snapshot.board.height(),
+            )),
+        );
+        gui.session = Some(session);
+        gui.apply_snapshot(snapshot);
+        gui
+    }
+
+    // Opens on a level select list for the pack, remembering solved puzzles in
+    // progress_path
+    pub fn puzzles(pack: PuzzlePack, progress_path: PathBuf) -> Self {
+        let first = &pack.puzzles[0];
+        let mut gui = GUI::new(
+            first.board.clone(),
+            first.unflagged_mines(),
+            Box::new(first.topology()),
+        );
+
+        // One window that fits the list and every puzzle in the pack
+        let width = pack
+            .puzzles
+            .iter()
+            .map(|p| p.board.width())
+            .fold(LEVEL_SELECT_WIDTH, i32::max);
+        let height = pack
+            .puzzles
+            .iter()
+            .map(|p| p.board.height())
+            .fold(pack.puzzles.len() as i32 + 3, i32::max);
+        gui.world_size = world_size(TileShape::Square, width, height, false);
+        gui.window.set_size(gui.world_size);
+
+        gui.puzzle_mode = Some(PuzzleMode {
+            solved: puzzle::load_progress(&progress_path),
+            pack,
+            progress_path,
+            current: 0,
+            choosing: true,
+            guessed: false,
+        });
+        gui
+    }
+
+    // Marks the game as the daily board for date, built from seed. Days that already
+    // have a recorded attempt play unranked
+    pub fn set_daily(&mut self, date: String, seed: u64) {
+        let played = daily::load_results(Path::new(daily::RESULTS_FILE))
+            .iter()
+            .any(|r| r.date == date);
+        self.ranked = !played;
+        self.daily = Some(DailyGame {
+            date,
+            seed,
+            first_attempt: !played,
+        });
+    }
+
+    // Practice games allow undoing the click that hit a mine
+    pub fn set_practice(&mut self, practice: bool) {
+        self.practice = practice;
+    }
+
+    // With question marks off, right click only toggles flags
+    pub fn set_question_marks(&mut self, question_marks: bool) {
+        self.question_marks = question_marks;
+    }
+
+    // On wrap-around boards, surrounds the board with faded copies of the
+    // opposite edges. Clicking a copy acts on the tile it shows
+    pub fn set_ghosts(&mut self, ghosts: bool) {
+        self.ghosts = ghosts && self.topology.wraps();
+        self.board_offset = if self.ghosts {
+            [PIXEL_SIZE, PIXEL_SIZE]
+        } else {
+            [0.0, 0.0]
+        };
+        self.world_size = world_size(
+            self.topology.tile_shape(),
+            self.width,
+            self.view_height,
+            self.ghosts,
+        );
+        self.window.set_size(self.world_size);
+    }
+
+    // Lets tiles hold up to max_per_tile mines and take as many flags
+    pub fn set_max_mines_per_tile(&mut self, max_per_tile: i32) {
+        self.max_per_tile = max_per_tile;
+    }
+
+    // Rule for the numbers on the board this game was built with
+    pub fn set_clue_rule(&mut self, clue_rule: ClueRule) {
+        self.clue_rule = clue_rule;
+    }
+
+    // How new games spread their mines
+    pub fn set_placement(&mut self, placement: Placement) {
+        self.placement = placement;
+    }
+
+    // Hands the board to the built-in player at moves_per_second. Games it plays are
+    // unranked, and puzzles, daily boards and shared games can't be watched
+    pub fn set_watch(&mut self, moves_per_second: f64) {
+        self.watch_speed = moves_per_second.clamp(WATCH_SPEEDS.0, WATCH_SPEEDS.1);
+        if self.session.is_none() && self.puzzle_mode.is_none() && self.daily.is_none() {
+            self.autoplay = Some(Autoplay {
+                last_move: Instant::now(),
+                queue: VecDeque::new(),
+            });
+            self.ranked = false;
+        }
+    }
+
+    fn toggle_watch(&mut self) {
+        if self.autoplay.is_some() {
+            self.autoplay = None;
+        } else {
+            self.set_watch(self.watch_speed);
+        }
+    }
+
+    fn apply_snapshot(&mut self, snapshot: Snapshot) {
+        self.shared_view = Some(snapshot.board);
+        self.game_result = snapshot.game_result;
+        self.current_mine_count = snapshot.mine_count;
+        self.lives = snapshot.lives;
+        self.hit_mine = snapshot.hit_mine;
+        self.players = snapshot.players;
+    }
+
     fn mouse_update(&mut self, m: [f64; 2]) {
-        self.mouse_pos = [
-            (m[0] / PIXEL_SIZE) as i32,
-            ((m[1] - PIXEL_SIZE * 2.0) / PIXEL_SIZE) as i32,
-        ];
+        let mut mouse_pos = tile_at(
+            self.topology.tile_shape(),
+            [m[0] - self.board_offset[0], m[1] - self.board_offset[1]],
+        );
+        if self.ghosts
+            && (-1..=self.width).contains(&mouse_pos[0])
+            && (-1..=self.height).contains(&mouse_pos[1])
+        {
+            mouse_pos = [
+                mouse_pos[0].rem_euclid(self.width),
+                mouse_pos[1].rem_euclid(self.height),
+            ];
+        }
+        if (0..self.view_height).contains(&mouse_pos[1]) {
+            mouse_pos[1] += self.layer * self.view_height;
+        } else if self.topology.layers() > 1 {
+            mouse_pos[1] = -1;
+        }
+
+        if mouse_pos != self.mouse_pos {
+            if let Some(session) = &mut self.session {
+                session.send_cursor(mouse_pos[0], mouse_pos[1]);
+            }
+        }
+        self.mouse_pos = mouse_pos;
+    }
+
+    fn mouse_on_board(&self) -> bool {
+        self.topology.contains(self.mouse_pos[0], self.mouse_pos[1])
+    }
+
+    // Tiles drawn pushed in while the left button is held: the tile under the cursor,
+    // or every tile a chord would open when it's a number and the rules allow chording
+    fn pressed_tiles(&self, view: &VisibleBoard) -> Vec<[i32; 2]> {
+        if !self.left_held || self.game_result != 0 || !self.mouse_on_board() {
+            return Vec::new();
+        }
+
+        let [x, y] = self.mouse_pos;
+        let closed = |tile| matches!(tile, VisibleTile::Hidden | VisibleTile::Questioned);
+        match view.tile(x, y) {
+            VisibleTile::Revealed(num) if num > 0 && self.clue_rule.allows_chording() => self
+                .topology
+                .neighbours(x, y)
+                .into_iter()
+                .filter(|[nx, ny]| closed(view.tile(*nx, *ny)))
+                .collect(),
+            tile if closed(tile) => vec![[x, y]],
+            _ => Vec::new(),
+        }
+    }
+
+    // What the player sees: the server's board in a shared game, and the mines once a
+    // game is lost
+    fn visible_board(&self) -> VisibleBoard {
+        if let Some(view) = &self.shared_view {
+            return view.clone();
+        }
+        if self.game_result == 1 {
+            let hit = self.topology.contains(self.hit_mine[0], self.hit_mine[1]);
+            VisibleBoard::after_game(&self.board, hit.then_some(self.hit_mine))
+        } else {
+            VisibleBoard::new(&self.board)
+        }
+    }
+
+    fn save_state(&self) -> BoardState {
+        BoardState {
+            board: self.board.clone(),
+            mine_count: self.current_mine_count,
+            game_result: self.game_result,
+            hit_mine: self.hit_mine,
+            last_click: self.last_click,
+        }
+    }
+
+    fn load_state(&mut self, state: BoardState) {
+        self.reveal_delays.clear();
+        if let Some(autoplay) = &mut self.autoplay {
+            autoplay.queue.clear();
+        }
+        self.board = state.board;
+        self.current_mine_count = state.mine_count;
+        self.game_result = state.game_result;
+        self.hit_mine = state.hit_mine;
+        self.last_click = state.last_click;
+        // Taking back the end of a game starts the clock again from where it stopped
+        if self.game_result == 0 {
+            if let Some(seconds) = self.finished.take() {
+                self.started = Instant::now().checked_sub(Duration::from_secs_f64(seconds));
+            }
+        }
+    }
+
+    // Records the state from before a move, skipping moves that changed nothing
+    fn push_history(&mut self, before: BoardState) {
+        if before.board != self.board || before.game_result != self.game_result {
+            self.last_click = Some(self.mouse_pos);
+            self.undo_stack.push(before);
+            self.redo_stack.clear();
+        }
+    }
+
+    fn undo(&mut self) {
+        // Only practice games may take back a lost game, a won game is final, and the
+        // daily board gets one try
+        if self.game_result == 2
+            || (self.game_result == 1 && !self.practice)
+            || self.daily.is_some()
+        {
+            return;
+        }
+
+        if let Some(state) = self.undo_stack.pop() {
+            let current = self.save_state();
+            self.redo_stack.push(current);
+            self.load_state(state);
+            self.ranked = false;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.redo_stack.pop() {
+            let current = self.save_state();
+            self.undo_stack.push(current);
+            self.load_state(state);
+        }
+    }
+
+    fn reveal_selected(&mut self) {
+        if let Some(session) = &mut self.session {
+            session.send_reveal(self.mouse_pos[0], self.mouse_pos[1]);
+            return;
+        }
+
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+            // Count the attempt now, so quitting a bad start doesn't give another
+            self.record_daily("unfinished");
+        }
+
+        let before = self.save_state();
+        if self.guessing() {
+            if let Some(mode) = &mut self.puzzle_mode {
+                mode.guessed = true;
+            }
+            self.hit_mine = [-1, -1];
+            self.game_result = 1;
+            self.push_history(before);
+            return;
+        }
+
+        let [x, y] = self.mouse_pos;
+        // Clicking a revealed number chords it
+        let opened = minesweeper::reveal_tile(&mut self.board, self.topology.as_ref(), x, y)
+            .and_then(|mut revealed| {
+                if self.board.is_revealed(x, y) {
+                    revealed.append(&mut minesweeper::cord_tile(
+                        &mut self.board,
+                        self.topology.as_ref(),
+                        self.clue_rule,
+                        x,
+                        y,
+                    )?);
+                }
+                Ok(revealed)
+            });
+        match opened {
+            Ok(revealed) => self.animate_reveal(revealed),
+            Err(mine) => {
+                self.hit_mine = mine;
+                self.game_result = 1;
+            }
+        }
+        self.push_history(before);
+    }
+
+    fn flag_selected(&mut self) {
+        if let Some(session) = &mut self.session {
+            // Work out the next mark locally, cycling as flag_tile does, and ask the
+            // server for exactly that
+            let [x, y] = self.mouse_pos;
+            let current = match &self.shared_view {
+                Some(view) if self.topology.contains(x, y) => view.tile(x, y),
+                _ => return,
+            };
+            let (flagged, questioned) = match current {
+                VisibleTile::Hidden => (true, false),
+                VisibleTile::Flagged(_) => (false, self.question_marks),
+                VisibleTile::Questioned => (false, false),
+                _ => return,
+            };
+            session.send_mark(x, y, flagged, questioned);
+            return;
+        }
+
+        let before = self.save_state();
+        self.current_mine_count = minesweeper::flag_tile(
+            &mut self.board,
+            self.mouse_pos[0],
+            self.mouse_pos[1],
+            self.current_mine_count,
+            self.question_marks,
+            self.max_per_tile,
+        );
+        self.push_history(before);
+    }
+
+    // Whether clicking the selected tile would open a tile the numbers haven't proven
+    // safe, in a puzzle that forbids it
+    fn guessing(&self) -> bool {
+        let puzzle = match &self.puzzle_mode {
+            Some(mode) if mode.pack.puzzles[mode.current].goal == Goal::NoGuessing => {
+                &mode.pack.puzzles[mode.current]
+            }
+            _ => return false,
+        };
+
+        let [x, y] = self.mouse_pos;
+        let tile = self.board.tile(x, y);
+        let neighbours = self.topology.neighbours(x, y);
+        let flags: i32 = neighbours
+            .iter()
+            .map(|[nx, ny]| self.board.get_flags(*nx, *ny))
+            .sum();
+        let opened: Vec<[i32; 2]> = if !tile.is_revealed() {
+            vec![[x, y]]
+        } else if tile.get_num() != 0 && flags == tile.get_num() {
+            // A chord opens every unflagged neighbour
+            neighbours
+                .into_iter()
+                .filter(|[nx, ny]| {
+                    let neighbour = self.board.tile(*nx, *ny);
+                    !neighbour.is_revealed() && !neighbour.is_flagged()
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if opened.is_empty() {
+            return false;
+        }
+
+        let deductions = solver::deduce(
+            &VisibleBoard::new(&self.board),
+            self.topology.as_ref(),
+            self.clue_rule,
+            self.max_per_tile,
+            puzzle.mine_count,
+        );
+        opened.iter().any(|p| !deductions.safe.contains(p))
+    }
+
+    // Every board the game went through, each with the tile clicked to reach it
+    fn game_history(&self) -> Vec<(MinesweeperBoard, Option<[i32; 2]>)> {
+        self.undo_stack
+            .iter()
+            .map(|state| (state.board.clone(), state.last_click))
+            .chain([(self.board.clone(), self.last_click)])
+            .collect()
+    }
+
+    fn review_mine_count(&self) -> i32 {
+        match &self.puzzle_mode {
+            Some(mode) => mode.pack.puzzles[mode.current].mine_count,
+            None => self.start_mine_count,
+        }
+    }
+
+    // Goes back over the moves that led to a lost game
+    fn review_game(&self) -> Review {
+        let hit = self.topology.contains(self.hit_mine[0], self.hit_mine[1]);
+        review::review(
+            &self.game_history(),
+            hit.then_some(self.hit_mine),
+            self.topology.as_ref(),
+            self.clue_rule,
+            self.max_per_tile,
+            self.review_mine_count(),
+        )
+    }
+
+    // Judges the click that lost the game, the only part of the review shown unasked.
+    // Only the last two boards are needed
+    fn fatal_move(&self) -> Option<Finding> {
+        let before = self.undo_stack.last()?;
+        let hit = self.topology.contains(self.hit_mine[0], self.hit_mine[1]);
+        review::fatal_move(
+            &[
+                (before.board.clone(), before.last_click),
+                (self.board.clone(), self.last_click),
+            ],
+            hit.then_some(self.hit_mine),
+            self.topology.as_ref(),
+            self.clue_rule,
+            self.max_per_tile,
+            self.review_mine_count(),
+        )
+        .map(|fatal| Finding {
+            move_number: self.undo_stack.len(),
+            ..fatal
+        })
+    }
+
+    fn start_puzzle(&mut self, n: usize) {
+        let mode = match &mut self.puzzle_mode {
+            Some(mode) => mode,
+            None => return,
+        };
+        mode.current = n;
+        mode.choosing = false;
+        mode.guessed = false;
+
+        let puzzle = &mode.pack.puzzles[n];
+        self.board = puzzle.board.clone();
+        self.topology = Box::new(puzzle.topology());
+        self.width = self.board.width();
+        self.height = self.board.height();
+        self.view_height = self.height;
+        self.start_mine_count = puzzle.unflagged_mines();
+        self.current_mine_count = self.start_mine_count;
+        self.tiles_to_win = safe_tiles(&self.board, self.topology.as_ref());
+        self.game_result = 0;
+        self.hit_mine = [-1, -1];
+        self.last_click = None;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.ranked = true;
+    }
+
+    fn new_game(&mut self) {
+        if let Some(mode) = &self.puzzle_mode {
+            self.start_puzzle(mode.current);
+            return;
+        }
+
+        let built = match &self.daily {
+            Some(daily) => minesweeper::build_minesweeper_board(
+                self.topology.as_ref(),
+                self.start_mine_count,
+                self.max_per_tile,
+                self.clue_rule,
+                &self.placement,
+                &mut daily::rng_for(daily.seed),
+            ),
+            None => minesweeper::build_minesweeper_board(
+                self.topology.as_ref(),
+                self.start_mine_count,
+                self.max_per_tile,
+                self.clue_rule,
+                &self.placement,
+                &mut rand::thread_rng(),
+            ),
+        };
+        // Placement rules can paint themselves into a corner, so keep playing the old
+        // board rather than quit
+        self.board = match built {
+            Ok(board) => board,
+            Err(e) => {
+                eprintln!("Could not build a new board: {}", e);
+                return;
+            }
+        };
+
+        self.game_result = 0;
+        self.last_click = None;
+        self.current_mine_count = self.start_mine_count;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.ranked = self.autoplay.is_none();
+        if let Some(autoplay) = &mut self.autoplay {
+            autoplay.queue.clear();
+        }
+        if let Some(daily) = &mut self.daily {
+            // Replays of the daily board don't count
+            daily.first_attempt &= self.started.is_none();
+            self.ranked = daily.first_attempt;
+        }
+        self.tiles_to_win = safe_tiles(&self.board, self.topology.as_ref());
+        self.started = None;
+        self.finished = None;
+        self.reveal_delays.clear();
+    }
+
+    // Makes the built-in player's next move once enough time has passed, and starts a
+    // new game a little after each one ends
+    fn autoplay_step(&mut self) {
+        let since_move = match &self.autoplay {
+            Some(autoplay) if !self.menu_open => autoplay.last_move.elapsed().as_secs_f64(),
+            _ => return,
+        };
+        let interval = 1.0 / self.watch_speed;
+        if since_move < interval || (self.game_result != 0 && since_move < WATCH_PAUSE) {
+            return;
+        }
+
+        if self.game_result != 0 {
+            self.new_game();
+        } else if let Some(next) = self.next_autoplay_move() {
+            self.mouse_pos = [next.x, next.y];
+            match next.action {
+                Action::Flag => self.flag_selected(),
+                Action::Reveal | Action::Chord => self.reveal_selected(),
+            }
+        }
+        if let Some(autoplay) = &mut self.autoplay {
+            autoplay.last_move = Instant::now();
+        }
+    }
+
+    // The next planned move that still needs making, planning more when they run out
+    fn next_autoplay_move(&mut self) -> Option<Move> {
+        let solver = Autosolver::new(self.clue_rule, self.max_per_tile);
+        let autoplay = self.autoplay.as_mut()?;
+        if autoplay.queue.is_empty() {
+            autoplay.queue = solver
+                .next_moves(
+                    &VisibleBoard::new(&self.board),
+                    self.topology.as_ref(),
+                    self.start_mine_count,
+                )
+                .into();
+        }
+
+        // A cascade since the moves were planned may already have opened their tiles
+        while let Some(next) = autoplay.queue.pop_front() {
+            if !self.board.is_revealed(next.x, next.y)
+                && (next.action != Action::Flag || !self.board.is_flagged(next.x, next.y))
+            {
+                return Some(next);
+            }
+        }
+        None
+    }
+
+    // Opens the tiles one after another, in the order the cascade reached them
+    fn animate_reveal(&mut self, opened: Vec<[i32; 2]>) {
+        let step = REVEAL_STEP.min(REVEAL_ANIMATION / opened.len().max(1) as f64);
+        self.reveal_delays = opened
+            .into_iter()
+            .enumerate()
+            .map(|(k, pos)| (pos, k as f64 * step))
+            .collect();
+        self.reveal_start = Instant::now();
+    }
+
+    // Seconds since the first reveal, stopping when the game ends
+    fn elapsed(&self) -> f64 {
+        match (self.finished, self.started) {
+            (Some(seconds), _) => seconds,
+            (None, Some(started)) => started.elapsed().as_secs_f64(),
+            (None, None) => 0.0,
+        }
+    }
+
+    // Saves how the day's first attempt at the daily board is going
+    fn record_daily(&self, result: &str) -> Option<DailyResult> {
+        let daily = self.daily.as_ref().filter(|d| d.first_attempt)?;
+        let daily_result = DailyResult {
+            date: daily.date.clone(),
+            seed: daily.seed,
+            result: result.to_string(),
+            seconds: self.elapsed(),
+            bbbv: minesweeper::three_bv(&self.board, self.topology.as_ref()),
+        };
+        if let Err(e) = daily::save_result(Path::new(daily::RESULTS_FILE), &daily_result) {
+            eprintln!("Could not save daily result: {}", e);
+        }
+        Some(daily_result)
+    }
+
+    // Knight moves only make sense on a flat, whole square board
+    fn knight_allowed(&self) -> bool {
+        let topology = self.launch_topology.as_ref().unwrap_or(&self.topology);
+        topology.tile_shape() == TileShape::Square
+            && topology.layers() == 1
+            && !topology.wraps()
+            && topology.positions().len() as i32 == self.width * self.height
+    }
+
+    // Starts a new game under the nth entry of MENU_RULES
+    fn choose_rules(&mut self, n: usize) {
+        let knight = n == 1;
+        if knight && !self.knight_allowed() {
+            return;
+        }
+
+        if knight && self.launch_topology.is_none() {
+            let knight_grid = Box::new(KnightGrid::new(self.width, self.height));
+            self.launch_topology = Some(std::mem::replace(&mut self.topology, knight_grid));
+        } else if !knight {
+            if let Some(topology) = self.launch_topology.take() {
+                self.topology = topology;
+            }
+        }
+        self.clue_rule = match n {
+            2 => ClueRule::Liar,
+            3 => ClueRule::ColourSplit,
+            _ => ClueRule::Standard,
+        };
+        self.menu_open = false;
+        self.new_game();
     }
 
     fn button_press(&mut self, b: ButtonArgs) {
-        if b.state == ButtonState::Press {
+        let choosing = self.puzzle_mode.as_ref().map(|m| m.choosing) == Some(true);
+        if b.state == ButtonState::Press && choosing {
+            if let Some(mode) = &mut self.puzzle_mode {
+                let last = mode.pack.puzzles.len() - 1;
+                match b.button {
+                    Button::Keyboard(Key::Up) => mode.current = mode.current.saturating_sub(1),
+                    Button::Keyboard(Key::Down) => mode.current = (mode.current + 1).min(last),
+                    Button::Keyboard(Key::Return) => self.new_game(),
+                    _ => {}
+                }
+            }
+        } else if b.state == ButtonState::Press && self.menu_open {
             match b.button {
-                Button::Mouse(MouseButton::Left) => {
-                    if self.game_result == 0 {
-                        let reveal_result = minesweeper::reveal_tile(
-                            &mut self.board,
-                            self.mouse_pos[0],
-                            self.mouse_pos[1],
-                        );
-                        if reveal_result == 1 {
-                            self.hit_mine = self.mouse_pos;
-                            self.game_result = 1;
-                        } else if self.board[self.mouse_pos[1] as usize][self.mouse_pos[0] as usize]
-                            .get_num()
-                            != 0
-                        {
-                            match minesweeper::cord_tile(
-                                &mut self.board,
-                                self.mouse_pos[0],
-                                self.mouse_pos[1],
-                            ) {
-                                Ok(_r) => {}
-                                Err(c) => {
-                                    if c != [-1, -1] {
-                                        self.hit_mine = c;
-                                        self.game_result = 1;
-                                    }
-                                }
-                            }
-                        }
+                Button::Keyboard(Key::D1) => self.choose_rules(0),
+                Button::Keyboard(Key::D2) => self.choose_rules(1),
+                Button::Keyboard(Key::D3) => self.choose_rules(2),
+                Button::Keyboard(Key::D4) => self.choose_rules(3),
+                Button::Keyboard(Key::N) => self.menu_open = false,
+                _ => {}
+            }
+        } else if b.state == ButtonState::Press {
+            let playing = self.game_result == 0 && self.mouse_on_board();
+            match b.button {
+                Button::Mouse(MouseButton::Left) if playing => self.left_held = true,
+                Button::Mouse(MouseButton::Right) if playing => self.flag_selected(),
+                Button::Keyboard(Key::LCtrl) if playing => self.flag_selected(),
+                Button::Keyboard(Key::R) => {
+                    if let Some(session) = &mut self.session {
+                        session.send_reset();
+                        return;
                     }
+
+                    self.new_game();
                 }
-                Button::Mouse(MouseButton::Right) => {
-                    if self.game_result == 0 {
-                        self.current_mine_count = minesweeper::flag_tile(
-                            &mut self.board,
-                            self.mouse_pos[0],
-                            self.mouse_pos[1],
-                            self.current_mine_count,
-                        );
+                // Shared games, puzzles and the daily board always use the standard rules
+                Button::Keyboard(Key::N)
+                    if self.session.is_none()
+                        && self.puzzle_mode.is_none()
+                        && self.daily.is_none() =>
+                {
+                    self.menu_open = true
+                }
+                Button::Keyboard(Key::L) => {
+                    if let Some(mode) = &mut self.puzzle_mode {
+                        mode.choosing = true;
                     }
                 }
-                Button::Keyboard(Key::LCtrl) => {
-                    if self.game_result == 0 {
-                        self.current_mine_count = minesweeper::flag_tile(
-                            &mut self.board,
-                            self.mouse_pos[0],
-                            self.mouse_pos[1],
-                            self.current_mine_count,
-                        );
+                Button::Keyboard(Key::Up) | Button::Keyboard(Key::PageUp) => {
+                    self.layer = (self.layer + 1).min(self.topology.layers() - 1);
+                }
+                Button::Keyboard(Key::Down) | Button::Keyboard(Key::PageDown) => {
+                    self.layer = (self.layer - 1).max(0);
+                }
+                Button::Keyboard(Key::A) => self.toggle_watch(),
+                // Shared games keep no history to review
+                Button::Keyboard(Key::V) if self.session.is_none() && self.game_result == 1 => {
+                    if self.review.is_none() {
+                        self.review = Some(self.review_game());
                     }
+                    self.review_open = !self.review_open;
                 }
-                Button::Keyboard(Key::R) => {
-                    self.game_result = 0;
-                    self.current_mine_count = self.start_mine_count;
-                    self.board = minesweeper::build_minesweeper_board(
-                        self.height,
-                        self.width,
-                        self.current_mine_count,
-                    );
+                Button::Keyboard(Key::Equals) | Button::Keyboard(Key::NumPadPlus) => {
+                    self.watch_speed = (self.watch_speed * 2.0).min(WATCH_SPEEDS.1);
+                }
+                Button::Keyboard(Key::Minus) | Button::Keyboard(Key::NumPadMinus) => {
+                    self.watch_speed = (self.watch_speed / 2.0).max(WATCH_SPEEDS.0);
                 }
+                Button::Keyboard(Key::Z) if self.session.is_none() => self.undo(),
+                Button::Keyboard(Key::Y) if self.session.is_none() => self.redo(),
                 _ => {}
             }
+        } else if b.button == Button::Mouse(MouseButton::Left) {
+            // A left click lands on the tile the button is let go over, so dragging off
+            // the board calls it off
+            let held = std::mem::take(&mut self.left_held);
+            let playing = self.game_result == 0 && self.mouse_on_board();
+            if held && playing && !choosing && !self.menu_open {
+                self.reveal_selected();
+            }
         }
     }
 
+    // Lines of the new game menu, level select list or review, when one is open
+    fn overlay_lines(&self) -> Option<Vec<(String, [f32; 4])>> {
+        if let Some(mode) = self.puzzle_mode.as_ref().filter(|m| m.choosing) {
+            let mut lines = vec![(mode.pack.name.to_uppercase(), color::BLACK)];
+            for (n, puzzle) in mode.pack.puzzles.iter().enumerate() {
+                let solved = if mode.solved.contains(&puzzle.name) {
+                    " - SOLVED"
+                } else {
+                    ""
+                };
+                let line_color = if n == mode.current {
+                    color::BLUE
+                } else {
+                    color::BLACK
+                };
+                lines.push((format!("{}{}", puzzle.name, solved), line_color));
+            }
+            lines.push(("UP/DOWN, ENTER TO PLAY".to_string(), color::grey(0.3)));
+            return Some(lines);
+        }
+
+        if let Some(review) = self.review.as_ref().filter(|_| self.review_open) {
+            let mut lines = vec![("REVIEW".to_string(), color::BLACK)];
+            if let Some(fatal) = review.fatal {
+                let [x, y] = fatal.position;
+                lines.push((format!("FATAL CLICK {},{}", x, y), color::BLACK));
+                lines.push((verdict_name(fatal.verdict).to_string(), color::RED));
+            }
+            match review.first_error {
+                Some(error) => {
+                    let [x, y] = error.position;
+                    lines.push((
+                        format!("FIRST ERROR MOVE {}", error.move_number),
+                        color::BLACK,
+                    ));
+                    lines.push((
+                        format!("{} {},{}", verdict_name(error.verdict), x, y),
+                        REVIEW_ERROR_COLOR,
+                    ));
+                }
+                None => lines.push(("NO LOGIC ERRORS".to_string(), color::BLACK)),
+            }
+            lines.push((format!("{} WRONG FLAGS", review.wrong_flags), color::BLACK));
+            if let Some(flag) = review.first_wrong_flag {
+                lines.push((
+                    format!("FIRST WRONG FLAG MOVE {}", flag.move_number),
+                    color::BLACK,
+                ));
+            }
+            lines.push((
+                format!("{} MINES MISSED", review.missed_mines),
+                color::BLACK,
+            ));
+            lines.push(("V TO CLOSE".to_string(), color::grey(0.3)));
+            return Some(lines);
+        }
+
+        if !self.menu_open {
+            return None;
+        }
+        let mut lines = vec![("NEW GAME".to_string(), color::BLACK)];
+        for (n, rules) in MENU_RULES.iter().enumerate() {
+            let line_color = if n != 1 || self.knight_allowed() {
+                color::BLACK
+            } else {
+                color::grey(0.6)
+            };
+            lines.push((format!("{} {}", n + 1, rules), line_color));
+        }
+        lines.push(("N TO CLOSE".to_string(), color::grey(0.3)));
+        Some(lines)
+    }
+
     fn render(&mut self, args: RenderArgs) {
+        let overlay_lines = self.overlay_lines();
+        let status = if let Some(mode) = &self.puzzle_mode {
+            Some(if mode.guessed {
+                "GUESSED".to_string()
+            } else if self.game_result == 2 {
+                "SOLVED".to_string()
+            } else {
+                mode.pack.puzzles[mode.current].name.to_uppercase()
+            })
+        } else if let Some(daily) = &self.daily {
+            let unranked = if daily.first_attempt {
+                ""
+            } else {
+                " - UNRANKED"
+            };
+            Some(format!("DAILY {}{}", daily.date, unranked))
+        } else if self.autoplay.is_some() {
+            Some(format!("WATCHING - {} MOVES/S", self.watch_speed))
+        } else {
+            // A lost game says whether the last click could have been avoided, and keeps
+            // saying it didn't count
+            let unranked = if self.ranked { "" } else { " - UNRANKED" };
+            self.fatal
+                .map(|fatal| format!("{}{}", verdict_name(fatal.verdict), unranked))
+        };
+        let review_error = self
+            .review
+            .as_ref()
+            .and_then(|review| review.first_error)
+            .map(|error| error.position);
+        let elapsed = self.elapsed();
+        let view = self.visible_board();
+        let pressed = self.pressed_tiles(&view);
+        let hovered = self.game_result == 0 && self.mouse_on_board() && overlay_lines.is_none();
+        let since_reveal = self.reveal_start.elapsed().as_secs_f64();
+        if self.reveal_delays.values().all(|d| *d <= since_reveal) {
+            self.reveal_delays.clear();
+        }
         self.gl.draw(args.viewport(), |c, gl| {
             clear(color::WHITE, gl);
 
@@ -191,105 +1110,504 @@ impl<'a> GUI<'a> {
                 )
                 .unwrap();
 
-            for j in 0..self.height {
-                for i in 0..self.width {
-                    let mut tile_color = color::grey(0.73);
-                    let mut border_color = color::WHITE;
-                    let mut border_radius = 1.5;
+            // Puzzles show their name or how they ended, daily games their date,
+            // watched games the player's speed and lost games how they were lost, other
+            // games whether an undo has made them not count
+            if let Some(status) = status.filter(|_| overlay_lines.is_none()) {
+                let (status_width, status_height) = get_text_size(&mut self.cache, &status, 20);
+                let width_offset = (self.world_size[0] - status_width) / 2.0;
+                let height_offset = ((PIXEL_SIZE * 2.0) - status_height) / 2.0;
+
+                Text::new_color(color::BLACK, 20)
+                    .draw(
+                        &status,
+                        &mut self.cache,
+                        &c.draw_state,
+                        c.transform
+                            .trans(width_offset, (PIXEL_SIZE * 2.0) - height_offset),
+                        gl,
+                    )
+                    .unwrap();
+            } else if !self.ranked {
+                let unranked_string = "UNRANKED";
+                let (unranked_width, unranked_height) =
+                    get_text_size(&mut self.cache, unranked_string, 20);
+                let width_offset = (self.world_size[0] - unranked_width) / 2.0;
+                let height_offset = ((PIXEL_SIZE * 2.0) - unranked_height) / 2.0;
+
+                Text::new_color(color::grey(0.3), 20)
+                    .draw(
+                        unranked_string,
+                        &mut self.cache,
+                        &c.draw_state,
+                        c.transform
+                            .trans(width_offset, (PIXEL_SIZE * 2.0) - height_offset),
+                        gl,
+                    )
+                    .unwrap();
+            }
+
+            // The daily board is raced against the clock
+            if self.daily.is_some() {
+                let time_string = format!("{:03}", elapsed as i32);
+                let (time_width, time_height) = get_text_size(&mut self.cache, &time_string, 40);
+                let width_offset = (self.world_size[0] * 5.0 / 6.0) - (time_width / 2.0);
+                let height_offset = ((PIXEL_SIZE * 2.0) - time_height) / 2.0;
+
+                Text::new_color(color::RED, 40)
+                    .draw(
+                        &time_string,
+                        &mut self.cache,
+                        &c.draw_state,
+                        c.transform
+                            .trans(width_offset, (PIXEL_SIZE * 2.0) - height_offset),
+                        gl,
+                    )
+                    .unwrap();
+            }
+
+            if let Some(lives) = self.lives {
+                let lives_string = format!("{} LIVES", lives);
+                let (lives_width, lives_height) = get_text_size(&mut self.cache, &lives_string, 20);
+                let width_offset = (self.world_size[0] - lives_width) / 2.0;
+                let height_offset = ((PIXEL_SIZE * 2.0) - lives_height) / 2.0;
+
+                Text::new_color(color::BLACK, 20)
+                    .draw(
+                        &lives_string,
+                        &mut self.cache,
+                        &c.draw_state,
+                        c.transform
+                            .trans(width_offset, (PIXEL_SIZE * 2.0) - height_offset),
+                        gl,
+                    )
+                    .unwrap();
+            }
+
+            // Scores as revealed tiles / correct flags, one line per player
+            for (n, player) in self.players.iter().enumerate() {
+                let score_string = format!(
+                    "P{} {} / {}",
+                    player.id + 1,
+                    player.revealed,
+                    player.correct_flags
+                );
+                let column = (n / 3) as f64;
+                let line = (n % 3) as f64;
+
+                Text::new_color(PLAYER_COLORS[player.id % PLAYER_COLORS.len()], 14)
+                    .draw(
+                        &score_string,
+                        &mut self.cache,
+                        &c.draw_state,
+                        c.transform.trans(
+                            (self.world_size[0] * 2.0 / 3.0) + (column * PIXEL_SIZE * 4.0),
+                            (PIXEL_SIZE * 0.6) + (line * PIXEL_SIZE * 0.6),
+                        ),
+                        gl,
+                    )
+                    .unwrap();
+            }
 
-                    let pos_x = i as f64 * PIXEL_SIZE;
-                    let pos_y = (2 + j) as f64 * PIXEL_SIZE;
-                    let current_tile = self.board[j as usize][i as usize];
+            let board_transform = c
+                .transform
+                .trans(self.board_offset[0], self.board_offset[1]);
+            let layer_offset = self.layer * self.view_height;
+            // Masked out tiles aren't drawn at all
+            let drawn_tiles: Vec<[i32; 2]> = drawn_tiles(self.width, self.view_height, self.ghosts)
+                .into_iter()
+                .filter(|[i, j]| {
+                    self.topology.contains(
+                        i.rem_euclid(self.width),
+                        j.rem_euclid(self.view_height) + layer_offset,
+                    )
+                })
+                .collect();
 
-                    if current_tile.is_revealed() {
+            if self.topology.layers() > 1 {
+                let layer_string = format!("LAYER {}/{}", self.layer + 1, self.topology.layers());
+                let (layer_width, layer_height) = get_text_size(&mut self.cache, &layer_string, 20);
+                let width_offset = (self.world_size[0] * 5.0 / 6.0) - (layer_width / 2.0);
+                let height_offset = ((PIXEL_SIZE * 2.0) - layer_height) / 2.0;
+
+                Text::new_color(color::BLACK, 20)
+                    .draw(
+                        &layer_string,
+                        &mut self.cache,
+                        &c.draw_state,
+                        c.transform
+                            .trans(width_offset, (PIXEL_SIZE * 2.0) - height_offset),
+                        gl,
+                    )
+                    .unwrap();
+            }
+
+            for &[draw_i, draw_j] in drawn_tiles.iter() {
+                let mut tile_color = color::grey(0.73);
+                let mut border_color = color::WHITE;
+                let mut border_radius = 1.5;
+
+                let [center_x, center_y] = tile_center(self.topology.tile_shape(), draw_i, draw_j);
+                let (i, view_j) = (
+                    draw_i.rem_euclid(self.width),
+                    draw_j.rem_euclid(self.view_height),
+                );
+                let ghost = [i, view_j] != [draw_i, draw_j];
+                let j = view_j + layer_offset;
+                let current_tile = view.tile(i, j);
+                let revealed = view.is_revealed(i, j)
+                    && self.reveal_delays.get(&[i, j]).unwrap_or(&0.0) <= &since_reveal;
+
+                match current_tile {
+                    VisibleTile::Mine(_) | VisibleTile::Exploded => {
+                        tile_color = if current_tile == VisibleTile::Exploded {
+                            color::RED
+                        } else {
+                            color::BLACK
+                        };
+                        border_color = color::RED;
+                        border_radius = 1.0;
+                    }
+                    _ if revealed || pressed.contains(&[i, j]) => {
                         border_color = color::grey(0.48);
                         border_radius = 0.5;
-                    } else if current_tile.is_flagged() {
+                    }
+                    VisibleTile::Flagged(_) | VisibleTile::WrongFlag => {
                         tile_color = color::LIME;
                         border_color = color::GREEN;
                         border_radius = 1.0;
                     }
+                    VisibleTile::Questioned => {
+                        tile_color = color::YELLOW;
+                        border_color = color::hex("c8a000");
+                        border_radius = 1.0;
+                    }
+                    _ => {}
+                }
 
-                    if current_tile.has_mine() && self.game_result == 1 {
-                        if [i, j] == self.hit_mine {
-                            tile_color = color::RED
-                        } else {
-                            tile_color = color::BLACK;
+                if hovered && self.mouse_pos == [i, j] && !pressed.contains(&[i, j]) {
+                    for part in tile_color.iter_mut().take(3) {
+                        *part += (1.0 - *part) * HOVER_LIGHTEN;
+                    }
+                }
+                if ghost {
+                    tile_color[3] = GHOST_ALPHA;
+                    border_color[3] = GHOST_ALPHA;
+                }
+
+                let transform = board_transform.trans(center_x, center_y);
+                match self.topology.tile_shape() {
+                    TileShape::Square => {
+                        let rect = Rectangle::new(tile_color).border(Border {
+                            color: border_color,
+                            radius: border_radius,
+                        });
+                        let dims = rectangle::centered_square(0.0, 0.0, PIXEL_SIZE / 2.0);
+
+                        rect.draw(dims, &c.draw_state, transform, gl);
+                    }
+                    TileShape::Hex => {
+                        let corners = hex_corners();
+                        Polygon::new(tile_color).draw(&corners, &c.draw_state, transform, gl);
+                        for k in 0..corners.len() {
+                            let [x1, y1] = corners[k];
+                            let [x2, y2] = corners[(k + 1) % corners.len()];
+                            line_from_to(
+                                border_color,
+                                border_radius,
+                                [x1, y1],
+                                [x2, y2],
+                                transform,
+                                gl,
+                            );
                         }
-                        border_color = color::RED;
-                        border_radius = 1.0;
                     }
+                }
+            }
 
-                    let rect = Rectangle::new(tile_color).border(Border {
-                        color: border_color,
-                        radius: border_radius,
-                    });
-                    let dims = rectangle::square(0.0, 0.0, PIXEL_SIZE);
+            // Dashed lines along the edges of wrap-around boards
+            if self.topology.wraps() {
+                for dash in wrap_edge_dashes(self.width, self.view_height) {
+                    line(WRAP_EDGE_COLOR, 1.5, dash, board_transform, gl);
+                }
+            }
 
-                    rect.draw(dims, &c.draw_state, c.transform.trans(pos_x, pos_y), gl);
+            // The first mistake a review found, when it's on the layer in view
+            if let Some([x, y]) = review_error
+                .filter(|[_, y]| (layer_offset..layer_offset + self.view_height).contains(y))
+            {
+                let [center_x, center_y] =
+                    tile_center(self.topology.tile_shape(), x, y - layer_offset);
+                let transform = board_transform.trans(center_x, center_y);
+                match self.topology.tile_shape() {
+                    TileShape::Square => {
+                        Rectangle::new_border(REVIEW_ERROR_COLOR, 2.0).draw(
+                            rectangle::centered_square(0.0, 0.0, (PIXEL_SIZE / 2.0) - 2.0),
+                            &c.draw_state,
+                            transform,
+                            gl,
+                        );
+                    }
+                    TileShape::Hex => {
+                        let corners = hex_corners();
+                        for k in 0..corners.len() {
+                            let [x1, y1] = corners[k];
+                            let [x2, y2] = corners[(k + 1) % corners.len()];
+                            line_from_to(
+                                REVIEW_ERROR_COLOR,
+                                2.0,
+                                [x1, y1],
+                                [x2, y2],
+                                transform,
+                                gl,
+                            );
+                        }
+                    }
                 }
             }
-            for j in 0..self.height {
-                for i in 0..self.width {
-                    let current_tile = self.board[j as usize][i as usize];
-
-                    if current_tile.is_revealed() {
-                        let tile_number = current_tile.get_num().to_string();
-                        let number_color = match tile_number.as_str() {
-                            "1" => color::BLUE,
-                            "2" => color::GREEN,
-                            "3" => color::RED,
-                            "4" => color::NAVY,
-                            "5" => color::MAROON,
-                            "6" => color::CYAN,
-                            "7" => color::PURPLE,
-                            "8" => color::grey(0.48),
-                            _ => color::grey(0.73),
-                        };
 
-                        let (num_width, num_height) =
-                            get_text_size(&mut self.cache, &tile_number, 20);
+            // Other players' cursors
+            let own_id = self.session.as_ref().map(|s| s.id());
+            for player in self.players.iter() {
+                if Some(player.id) == own_id
+                    || player.cursor[0] < 0
+                    || player.cursor[1] < 0
+                    || player.cursor[0] >= self.width
+                    || player.cursor[1] >= self.height
+                {
+                    continue;
+                }
+
+                let [center_x, center_y] = tile_center(
+                    self.topology.tile_shape(),
+                    player.cursor[0],
+                    player.cursor[1],
+                );
+                Rectangle::new_border(PLAYER_COLORS[player.id % PLAYER_COLORS.len()], 2.0).draw(
+                    rectangle::centered_square(0.0, 0.0, (PIXEL_SIZE / 2.0) - 2.0),
+                    &c.draw_state,
+                    board_transform.trans(center_x, center_y),
+                    gl,
+                );
+            }
+
+            for &[draw_i, draw_j] in drawn_tiles.iter() {
+                let (i, view_j) = (
+                    draw_i.rem_euclid(self.width),
+                    draw_j.rem_euclid(self.view_height),
+                );
+                let ghost = [i, view_j] != [draw_i, draw_j];
+                let j = view_j + layer_offset;
+                let current_tile = view.tile(i, j);
+                let revealed = view.is_revealed(i, j)
+                    && self.reveal_delays.get(&[i, j]).unwrap_or(&0.0) <= &since_reveal;
+                let [center_x, center_y] = tile_center(self.topology.tile_shape(), draw_i, draw_j);
+
+                // Faded numbers from the layers below (top left) and above (bottom right)
+                for (direction, corner) in [(-1, [-0.45, -0.1]), (1, [0.15, 0.4])] {
+                    let layer = self.layer + direction;
+                    if layer < 0 || layer >= self.topology.layers() {
+                        continue;
+                    }
+
+                    let other = view.get_num(i, view_j + layer * self.view_height);
+                    if other != 0 {
+                        Text::new_color([0.0, 0.0, 0.0, GHOST_ALPHA], 10)
+                            .draw(
+                                &other.to_string(),
+                                &mut self.cache,
+                                &c.draw_state,
+                                board_transform.trans(
+                                    center_x + (corner[0] * PIXEL_SIZE),
+                                    center_y + (corner[1] * PIXEL_SIZE),
+                                ),
+                                gl,
+                            )
+                            .unwrap();
+                    }
+                }
 
-                        let width_offset = (PIXEL_SIZE - num_width) / 2.0;
-                        let height_offset = (PIXEL_SIZE - num_height) / 2.0;
+                // Colour split clues show the mines in line with the tile in red on the
+                // left and the diagonal ones in blue on the right
+                if revealed && self.clue_rule == ClueRule::ColourSplit && view.get_num(i, j) != 0 {
+                    let split = view.get_split(i, j);
+                    let parts = [
+                        (split, color::RED, -0.25),
+                        (view.get_num(i, j) - split, color::BLUE, 0.25),
+                    ];
+                    for (count, mut part_color, side) in parts {
+                        if ghost {
+                            part_color[3] = GHOST_ALPHA;
+                        }
 
-                        let pos_x = (i as f64 * PIXEL_SIZE) + width_offset;
-                        let pos_y = ((j as f64 * PIXEL_SIZE) + (PIXEL_SIZE * 3.0)) - height_offset;
-                        Text::new_color(number_color, 20)
+                        let part = count.to_string();
+                        let (part_width, part_height) = get_text_size(&mut self.cache, &part, 14);
+                        Text::new_color(part_color, 14)
                             .draw(
-                                &*tile_number,
+                                &part,
                                 &mut self.cache,
                                 &c.draw_state,
-                                c.transform.trans(pos_x, pos_y),
+                                board_transform.trans(
+                                    center_x + (side * PIXEL_SIZE) - (part_width / 2.0),
+                                    center_y + (part_height / 2.0),
+                                ),
                                 gl,
                             )
                             .unwrap();
                     }
+                    continue;
+                }
+
+                // Wrong flags are crossed out
+                if current_tile == VisibleTile::WrongFlag {
+                    let reach = PIXEL_SIZE * 0.3;
+                    let mut cross_color = color::BLACK;
+                    if ghost {
+                        cross_color[3] = GHOST_ALPHA;
+                    }
+                    let transform = board_transform.trans(center_x, center_y);
+                    line_from_to(
+                        cross_color,
+                        1.5,
+                        [-reach, -reach],
+                        [reach, reach],
+                        transform,
+                        gl,
+                    );
+                    line_from_to(
+                        cross_color,
+                        1.5,
+                        [reach, -reach],
+                        [-reach, reach],
+                        transform,
+                        gl,
+                    );
+                    continue;
+                }
+
+                let tile_text = match current_tile {
+                    VisibleTile::Revealed(num) if revealed => Some(num.to_string()),
+                    VisibleTile::Mine(mines) if mines > 1 => Some(format!("x{}", mines)),
+                    VisibleTile::Flagged(flags) if flags > 1 => Some(format!("F{}", flags)),
+                    VisibleTile::Questioned if !pressed.contains(&[i, j]) => Some("?".to_string()),
+                    _ => None,
+                };
+
+                if let Some(tile_number) = tile_text {
+                    let mut number_color = match tile_number.as_str() {
+                        "1" => color::BLUE,
+                        "2" => color::GREEN,
+                        "3" => color::RED,
+                        "4" => color::NAVY,
+                        "5" => color::MAROON,
+                        "6" => color::CYAN,
+                        "7" => color::PURPLE,
+                        "8" => color::grey(0.48),
+                        "0" => color::grey(0.73),
+                        _ if tile_number.starts_with('x') => color::WHITE,
+                        _ => color::BLACK,
+                    };
+                    if ghost {
+                        number_color[3] = GHOST_ALPHA;
+                    }
+
+                    let (num_width, num_height) = get_text_size(&mut self.cache, &tile_number, 20);
+
+                    let pos_x = center_x - (num_width / 2.0);
+                    let pos_y = center_y + (num_height / 2.0);
+                    Text::new_color(number_color, 20)
+                        .draw(
+                            &tile_number,
+                            &mut self.cache,
+                            &c.draw_state,
+                            board_transform.trans(pos_x, pos_y),
+                            gl,
+                        )
+                        .unwrap();
+                }
+            }
+
+            if let Some(lines) = &overlay_lines {
+                Rectangle::new([1.0, 1.0, 1.0, 0.85]).draw(
+                    [
+                        0.0,
+                        PIXEL_SIZE * 2.0,
+                        self.world_size[0],
+                        self.world_size[1],
+                    ],
+                    &c.draw_state,
+                    c.transform,
+                    gl,
+                );
+
+                for (n, (menu_line, line_color)) in lines.iter().enumerate() {
+                    let (line_width, _) = get_text_size(&mut self.cache, menu_line, 20);
+                    Text::new_color(*line_color, 20)
+                        .draw(
+                            menu_line,
+                            &mut self.cache,
+                            &c.draw_state,
+                            c.transform.trans(
+                                (self.world_size[0] - line_width) / 2.0,
+                                (PIXEL_SIZE * 3.5) + (n as f64 * PIXEL_SIZE),
+                            ),
+                            gl,
+                        )
+                        .unwrap();
                 }
             }
         })
     }
 
     fn update(&mut self) {
-        let mut revealed_tiles = 0;
-
-        for j in 0..self.height {
-            for i in 0..self.width {
-                if !(self.board[j as usize][i as usize].has_mine())
-                    && self.board[j as usize][i as usize].is_revealed()
-                {
-                    revealed_tiles += 1;
-                }
+        // The server decides the outcome of shared games
+        if let Some(session) = &self.session {
+            if let Some(snapshot) = session.poll() {
+                self.apply_snapshot(snapshot);
             }
+            return;
         }
-        if self.tiles_to_win == revealed_tiles {
+
+        if self.tiles_to_win == minesweeper::revealed_safe_tiles(&self.board) {
             for i in 0..self.height {
                 for j in 0..self.width {
-                    self.board[i as usize][j as usize].set_flagged(true);
+                    self.board.set_flagged(j, i, true);
                 }
             }
             self.game_result = 2;
+
+            if let Some(mode) = &mut self.puzzle_mode {
+                let name = &mode.pack.puzzles[mode.current].name;
+                if !mode.choosing && !mode.solved.contains(name) {
+                    mode.solved.push(name.clone());
+                    if let Err(e) = puzzle::save_progress(&mode.progress_path, &mode.solved) {
+                        eprintln!("Could not save puzzle progress: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Undoing or starting again takes the review away
+        if self.game_result != 1 {
+            self.fatal = None;
+            self.review = None;
+            self.review_open = false;
+        }
+
+        if self.game_result != 0 && self.started.is_some() && self.finished.is_none() {
+            self.finished = Some(self.elapsed());
+            if self.game_result == 1 {
+                // Only the last click is judged now, since the full review runs the
+                // solver over every move and would stall the frame on large boards
+                self.fatal = self.fatal_move();
+            }
+            let result = if self.game_result == 2 { "won" } else { "lost" };
+            if let Some(daily_result) = self.record_daily(result) {
+                println!("{}", daily_result.share_text());
+            }
         }
+
+        self.autoplay_step();
     }
 
     pub fn run(&mut self) {
@@ -314,15 +1632,135 @@ impl<'a> GUI<'a> {
     }
 }
 
+// Size of the window, with room for a ring of ghost tiles around wrap-around boards
+fn world_size(shape: TileShape, width: i32, height: i32, ghosts: bool) -> [f64; 2] {
+    let margin = if ghosts { PIXEL_SIZE * 2.0 } else { 0.0 };
+    match shape {
+        TileShape::Square => [
+            (PIXEL_SIZE * width as f64) + margin,
+            (PIXEL_SIZE * height as f64) + (PIXEL_SIZE * 2.0) + margin,
+        ],
+        TileShape::Hex => [
+            (PIXEL_SIZE * (width as f64 + 0.5)),
+            (HEX_RADIUS * (2.0 + 1.5 * (height - 1) as f64)) + (PIXEL_SIZE * 2.0),
+        ],
+    }
+}
+
+// Half tile dashes along the four edges of a wrap-around board, in board coordinates.
+// The top and bottom edges run the board's width, one dash per column, and the left
+// and right edges its height, one dash per row
+fn wrap_edge_dashes(width: i32, height: i32) -> Vec<[f64; 4]> {
+    let top = PIXEL_SIZE * 2.0;
+    let bottom = top + PIXEL_SIZE * height as f64;
+    let right = PIXEL_SIZE * width as f64;
+    let dash = PIXEL_SIZE / 2.0;
+
+    let mut dashes = Vec::new();
+    for column in 0..width {
+        let x = (column as f64 + 0.25) * PIXEL_SIZE;
+        dashes.push([x, top, x + dash, top]);
+        dashes.push([x, bottom, x + dash, bottom]);
+    }
+    for row in 0..height {
+        let y = top + (row as f64 + 0.25) * PIXEL_SIZE;
+        dashes.push([0.0, y, 0.0, y + dash]);
+        dashes.push([right, y, right, y + dash]);
+    }
+    dashes
+}
+
+// Tiles to draw, including the ghost ring one tile outside the board
+fn drawn_tiles(width: i32, height: i32, ghosts: bool) -> Vec<[i32; 2]> {
+    let margin = ghosts as i32;
+    let mut tiles = Vec::new();
+    for j in -margin..height + margin {
+        for i in -margin..width + margin {
+            tiles.push([i, j]);
+        }
+    }
+    tiles
+}
+
+// Centre of a tile in window coordinates, below the two tile high header
+fn tile_center(shape: TileShape, i: i32, j: i32) -> [f64; 2] {
+    match shape {
+        TileShape::Square => [(i as f64 + 0.5) * PIXEL_SIZE, (j as f64 + 2.5) * PIXEL_SIZE],
+        TileShape::Hex => [
+            (i as f64 + 0.5 + 0.5 * (j % 2) as f64) * PIXEL_SIZE,
+            (PIXEL_SIZE * 2.0) + HEX_RADIUS + (1.5 * HEX_RADIUS * j as f64),
+        ],
+    }
+}
+
+// The tile under a window position, which may be off the board
+fn tile_at(shape: TileShape, m: [f64; 2]) -> [i32; 2] {
+    match shape {
+        TileShape::Square => [
+            (m[0] / PIXEL_SIZE).floor() as i32,
+            ((m[1] - PIXEL_SIZE * 2.0) / PIXEL_SIZE).floor() as i32,
+        ],
+        TileShape::Hex => {
+            // Convert to fractional axial coordinates relative to the first hex,
+            // round to the nearest hex in cube space, then back to odd row offsets
+            let x = m[0] - (PIXEL_SIZE / 2.0);
+            let y = m[1] - (PIXEL_SIZE * 2.0) - HEX_RADIUS;
+            let q = ((3.0_f64.sqrt() / 3.0 * x) - (y / 3.0)) / HEX_RADIUS;
+            let r = (2.0 / 3.0 * y) / HEX_RADIUS;
+            let s = -q - r;
+
+            let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+            let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+            if dq > dr && dq > ds {
+                rq = -rr - rs;
+            } else if dr > ds {
+                rr = -rq - rs;
+            }
+
+            let row = rr as i32;
+            [rq as i32 + (row - (row & 1)) / 2, row]
+        }
+    }
+}
+
+// Corners of a pointy-top hex around the origin
+fn hex_corners() -> [[f64; 2]; 6] {
+    let mut corners = [[0.0; 2]; 6];
+    for (k, corner) in corners.iter_mut().enumerate() {
+        let angle = (60.0 * k as f64 - 30.0).to_radians();
+        *corner = [HEX_RADIUS * angle.cos(), HEX_RADIUS * angle.sin()];
+    }
+    corners
+}
+
+// How a review describes a move, short enough for the status line
+fn verdict_name(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::ForcedGuess => "FORCED GUESS",
+        Verdict::AvoidableGuess => "AVOIDABLE GUESS",
+        Verdict::ProvenMine => "PROVEN MINE",
+        Verdict::WrongFlag => "WRONG FLAG",
+    }
+}
+
+// Tiles without a mine, all of which must be revealed to win
+fn safe_tiles(board: &MinesweeperBoard, topology: &dyn Topology) -> i32 {
+    topology
+        .positions()
+        .iter()
+        .filter(|[x, y]| !board.has_mine(*x, *y))
+        .count() as i32
+}
+
 fn get_text_size(cache: &mut Cache<(), Texture>, str: &str, font_size: u32) -> (f64, f64) {
     let mut width = 0.0;
     let mut height = 0.0;
     for ch in str.chars() {
         let character = cache.character(font_size, ch).ok().unwrap();
-        width += (character.advance_width() + character.left()) as f64;
+        width += character.advance_width() + character.left();
 
-        if (character.advance_height() + character.top()) as f64 > height {
-            height = (character.advance_height() + character.top()) as f64;
+        if character.advance_height() + character.top() > height {
+            height = character.advance_height() + character.top();
         }
     }
 
@@ -0,0 +1,147 @@
+use crate::minesweeper::MinesweeperBoard;
+
+// What a player can see of one tile
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum VisibleTile {
+    Hidden,
+    // The number of flags, more than one only where tiles may hold several mines
+    Flagged(i32),
+    Questioned,
+    // The number shown
+    Revealed(i32),
+    // The mine that ended the game
+    Exploded,
+    // After the game, an unflagged tile holding this many mines
+    Mine(i32),
+    // After the game, a flag on a tile without that many mines
+    WrongFlag,
+}
+
+// A board as the player sees it, with no way to ask where the hidden mines are. Bots,
+// renderers, bindings and the network protocol all work from this, so reading a mine
+// early means going back to the MinesweeperBoard on purpose
+#[derive(Clone, PartialEq)]
+pub struct VisibleBoard {
+    width: i32,
+    height: i32,
+    tiles: Vec<VisibleTile>,
+    // Under ClueRule::ColourSplit, the part of each revealed number in the same row or
+    // column
+    splits: Vec<i32>,
+}
+
+impl VisibleBoard {
+    // The board while the game is being played
+    pub fn new(board: &MinesweeperBoard) -> Self {
+        let mut view = VisibleBoard::hidden(board.width(), board.height());
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                let n = view.index(x, y);
+                view.tiles[n] = if board.is_revealed(x, y) {
+                    view.splits[n] = board.get_split(x, y);
+                    VisibleTile::Revealed(board.get_num(x, y))
+                } else if board.is_flagged(x, y) {
+                    VisibleTile::Flagged(board.get_flags(x, y))
+                } else if board.is_questioned(x, y) {
+                    VisibleTile::Questioned
+                } else {
+                    VisibleTile::Hidden
+                };
+            }
+        }
+        view
+    }
+
+    // The board once the game is over, showing the mines that weren't flagged, the
+    // flags that were wrong and the mine at hit_mine that ended it
+    pub fn after_game(board: &MinesweeperBoard, hit_mine: Option<[i32; 2]>) -> Self {
+        let mut view = VisibleBoard::new(board);
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                if hit_mine == Some([x, y]) {
+                    view.set_tile(x, y, VisibleTile::Exploded);
+                } else if !board.is_revealed(x, y) && board.get_flags(x, y) > board.get_mines(x, y)
+                {
+                    view.set_tile(x, y, VisibleTile::WrongFlag);
+                } else if board.has_mine(x, y) && !board.is_flagged(x, y) {
+                    view.set_tile(x, y, VisibleTile::Mine(board.get_mines(x, y)));
+                }
+            }
+        }
+        view
+    }
+
+    // A board of hidden tiles, to be filled in with set_tile
+    pub fn hidden(width: i32, height: i32) -> Self {
+        let size = (width.max(0) * height.max(0)) as usize;
+        VisibleBoard {
+            width: width.max(0),
+            height: height.max(0),
+            tiles: vec![VisibleTile::Hidden; size],
+            splits: vec![0; size],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    pub fn tile(&self, x: i32, y: i32) -> VisibleTile {
+        self.tiles[self.index(x, y)]
+    }
+
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: VisibleTile) {
+        let n = self.index(x, y);
+        self.tiles[n] = tile;
+    }
+
+    pub fn set_split(&mut self, x: i32, y: i32, split: i32) {
+        let n = self.index(x, y);
+        self.splits[n] = split;
+    }
+
+    // Every tile in row order
+    pub fn tiles(&self) -> impl Iterator<Item = VisibleTile> + '_ {
+        self.tiles.iter().copied()
+    }
+
+    // Getters
+    pub fn is_revealed(&self, x: i32, y: i32) -> bool {
+        matches!(self.tile(x, y), VisibleTile::Revealed(_))
+    }
+
+    // The number on a revealed tile, 0 elsewhere
+    pub fn get_num(&self, x: i32, y: i32) -> i32 {
+        match self.tile(x, y) {
+            VisibleTile::Revealed(num) => num,
+            _ => 0,
+        }
+    }
+
+    pub fn get_split(&self, x: i32, y: i32) -> i32 {
+        self.splits[self.index(x, y)]
+    }
+
+    pub fn is_flagged(&self, x: i32, y: i32) -> bool {
+        matches!(self.tile(x, y), VisibleTile::Flagged(_))
+    }
+
+    pub fn get_flags(&self, x: i32, y: i32) -> i32 {
+        match self.tile(x, y) {
+            VisibleTile::Flagged(flags) => flags,
+            _ => 0,
+        }
+    }
+
+    pub fn is_questioned(&self, x: i32, y: i32) -> bool {
+        self.tile(x, y) == VisibleTile::Questioned
+    }
+}
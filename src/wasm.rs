@@ -0,0 +1,131 @@
+// JavaScript bindings for the browser, built without the GUI:
+//
+//   cargo build --lib --release --target wasm32-unknown-unknown \
+//       --no-default-features --features wasm
+//   wasm-bindgen --target web --out-dir web/pkg \
+//       target/wasm32-unknown-unknown/release/minesweeper.wasm
+//
+// web/index.html is a canvas frontend on top of them
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use wasm_bindgen::prelude::*;
+
+use crate::game::{Game, Status};
+use crate::visible::VisibleTile;
+
+// Values in Game.view() for tiles that don't show a number
+const HIDDEN: i8 = -1;
+const FLAGGED: i8 = -2;
+const QUESTIONED: i8 = -3;
+const MINE: i8 = -4;
+const EXPLODED: i8 = -5;
+const WRONG_FLAG: i8 = -6;
+
+// A single player game on a square board
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    game: Game,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    // The same seed always gives the same board. Without one the board is random
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        width: i32,
+        height: i32,
+        mines: i32,
+        seed: Option<u32>,
+    ) -> Result<WasmGame, JsError> {
+        if width <= 0 || height <= 0 {
+            return Err(JsError::new("Sizes must be positive"));
+        }
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed as u64),
+            None => StdRng::from_entropy(),
+        };
+        let game = Game::new(width, height, mines, 1, &mut rng)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(WasmGame { game })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> i32 {
+        self.game.width()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> i32 {
+        self.game.height()
+    }
+
+    #[wasm_bindgen(getter, js_name = mineCount)]
+    pub fn mine_count(&self) -> i32 {
+        self.game.mine_count()
+    }
+
+    // Mines less flags placed, as the counter shows
+    #[wasm_bindgen(getter, js_name = minesLeft)]
+    pub fn mines_left(&self) -> i32 {
+        self.game.mines_left()
+    }
+
+    // "playing", "lost" or "won"
+    #[wasm_bindgen(getter)]
+    pub fn status(&self) -> String {
+        match self.game.status() {
+            Status::Playing => "playing",
+            Status::Lost => "lost",
+            Status::Won => "won",
+        }
+        .to_string()
+    }
+
+    // Reveals the tile, returning how many tiles opened. Revealing a mine loses the
+    // game
+    pub fn reveal(&mut self, x: i32, y: i32) -> Result<u32, JsError> {
+        self.check(x, y)?;
+        Ok(self.game.reveal(x, y).len() as u32)
+    }
+
+    // Reveals the neighbours of a revealed number with as many flags around it
+    pub fn chord(&mut self, x: i32, y: i32) -> Result<u32, JsError> {
+        self.check(x, y)?;
+        Ok(self.game.chord(x, y).len() as u32)
+    }
+
+    // Toggles a flag on a hidden tile, returning whether it is now flagged
+    pub fn flag(&mut self, x: i32, y: i32) -> Result<bool, JsError> {
+        self.check(x, y)?;
+        Ok(self.game.flag(x, y) > 0)
+    }
+
+    // What the player sees as an Int8Array, row after row: the number on revealed
+    // tiles, -1 hidden, -2 flagged and -3 question marked. Once the game is over, -4
+    // marks the unflagged mines, -5 the one that was hit and -6 flags on safe tiles
+    pub fn view(&self) -> Vec<i8> {
+        self.game
+            .view()
+            .tiles()
+            .map(|tile| match tile {
+                VisibleTile::Revealed(num) => num.min(i8::MAX as i32) as i8,
+                VisibleTile::Hidden => HIDDEN,
+                VisibleTile::Flagged(_) => FLAGGED,
+                VisibleTile::Questioned => QUESTIONED,
+                VisibleTile::Mine(_) => MINE,
+                VisibleTile::Exploded => EXPLODED,
+                VisibleTile::WrongFlag => WRONG_FLAG,
+            })
+            .collect()
+    }
+}
+
+impl WasmGame {
+    fn check(&self, x: i32, y: i32) -> Result<(), JsError> {
+        if self.game.contains(x, y) {
+            Ok(())
+        } else {
+            Err(JsError::new(&format!("({}, {}) is off the board", x, y)))
+        }
+    }
+}
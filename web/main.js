@@ -0,0 +1,111 @@
+// Canvas frontend for the engine built to web/pkg. Left click reveals, or chords on a
+// number, and right click toggles a flag
+import init, { Game } from "./pkg/minesweeper.js";
+
+const TILE_SIZE = 32;
+const LEVELS = {
+  beginner: [9, 9, 10],
+  intermediate: [16, 16, 40],
+  expert: [30, 16, 99],
+};
+// Values in Game.view() for tiles that don't show a number
+const HIDDEN = -1;
+const FLAGGED = -2;
+const QUESTIONED = -3;
+const MINE = -4;
+const EXPLODED = -5;
+const WRONG_FLAG = -6;
+// As the desktop GUI draws them
+const NUMBER_COLORS = ["#bababa", "#0000ff", "#008000", "#ff0000", "#000080",
+  "#800000", "#00ffff", "#800080", "#7a7a7a"];
+
+const canvas = document.getElementById("board");
+const context = canvas.getContext("2d");
+const level = document.getElementById("level");
+const minesLeft = document.getElementById("mines-left");
+const status = document.getElementById("status");
+let game;
+
+function newGame() {
+  const [width, height, mines] = LEVELS[level.value];
+  // The board lives in wasm memory, which isn't collected with the JS object
+  game?.free();
+  game = new Game(width, height, mines);
+  canvas.width = width * TILE_SIZE;
+  canvas.height = height * TILE_SIZE;
+  draw();
+}
+
+function drawTile(x, y, fill, border, borderWidth) {
+  context.fillStyle = fill;
+  context.fillRect(x * TILE_SIZE, y * TILE_SIZE, TILE_SIZE, TILE_SIZE);
+  context.strokeStyle = border;
+  context.lineWidth = borderWidth;
+  context.strokeRect(x * TILE_SIZE + borderWidth / 2, y * TILE_SIZE + borderWidth / 2,
+    TILE_SIZE - borderWidth, TILE_SIZE - borderWidth);
+}
+
+function draw() {
+  const view = game.view();
+  context.font = `bold ${TILE_SIZE * 0.6}px sans-serif`;
+  context.textAlign = "center";
+  context.textBaseline = "middle";
+  for (let y = 0; y < game.height; y++) {
+    for (let x = 0; x < game.width; x++) {
+      const value = view[y * game.width + x];
+      if (value === MINE) {
+        drawTile(x, y, "#000000", "#ff0000", 2);
+      } else if (value === EXPLODED) {
+        drawTile(x, y, "#ff0000", "#ff0000", 2);
+      } else if (value === FLAGGED) {
+        drawTile(x, y, "#00ff00", "#008000", 2);
+      } else if (value === WRONG_FLAG) {
+        // A flag crossed out
+        drawTile(x, y, "#00ff00", "#008000", 2);
+        context.strokeStyle = "#000000";
+        context.beginPath();
+        context.moveTo(x * TILE_SIZE + 6, y * TILE_SIZE + 6);
+        context.lineTo((x + 1) * TILE_SIZE - 6, (y + 1) * TILE_SIZE - 6);
+        context.moveTo((x + 1) * TILE_SIZE - 6, y * TILE_SIZE + 6);
+        context.lineTo(x * TILE_SIZE + 6, (y + 1) * TILE_SIZE - 6);
+        context.stroke();
+      } else if (value === QUESTIONED) {
+        drawTile(x, y, "#ffff00", "#c8a000", 2);
+      } else if (value === HIDDEN) {
+        drawTile(x, y, "#bababa", "#ffffff", 3);
+      } else {
+        drawTile(x, y, "#bababa", "#7a7a7a", 1);
+        if (value > 0) {
+          context.fillStyle = NUMBER_COLORS[Math.min(value, NUMBER_COLORS.length - 1)];
+          context.fillText(value, (x + 0.5) * TILE_SIZE, (y + 0.5) * TILE_SIZE);
+        }
+      }
+    }
+  }
+  minesLeft.textContent = `Mines: ${game.minesLeft}`;
+  status.textContent = { playing: "", lost: "Game over", won: "Cleared!" }[game.status];
+}
+
+canvas.addEventListener("mousedown", (event) => {
+  const x = Math.floor(event.offsetX / TILE_SIZE);
+  const y = Math.floor(event.offsetY / TILE_SIZE);
+  if (x < 0 || y < 0 || x >= game.width || y >= game.height) {
+    return;
+  }
+  if (event.button === 0) {
+    if (game.view()[y * game.width + x] >= 0) {
+      game.chord(x, y);
+    } else {
+      game.reveal(x, y);
+    }
+  } else if (event.button === 2) {
+    game.flag(x, y);
+  }
+  draw();
+});
+canvas.addEventListener("contextmenu", (event) => event.preventDefault());
+document.getElementById("new-game").addEventListener("click", newGame);
+level.addEventListener("change", newGame);
+
+await init();
+newGame();
@@ -0,0 +1,71 @@
+// Runs the bindings under node. Build them for node first:
+//
+//   cargo build --lib --release --target wasm32-unknown-unknown \
+//       --no-default-features --features wasm
+//   wasm-bindgen --target nodejs --out-dir web/pkg-node \
+//       target/wasm32-unknown-unknown/release/minesweeper.wasm
+//   node --test web/tests
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { createRequire } from "node:module";
+
+const { Game } = createRequire(import.meta.url)("../pkg-node/minesweeper.js");
+
+test("same seed gives the same board", () => {
+  const a = new Game(9, 9, 10, 5);
+  const b = new Game(9, 9, 10, 5);
+  a.reveal(4, 4);
+  b.reveal(4, 4);
+  assert.deepEqual(Array.from(a.view()), Array.from(b.view()));
+});
+
+test("no mines clears in one reveal", () => {
+  const game = new Game(4, 3, 0, 1);
+  assert.equal(game.view().length, 12);
+  assert.equal(game.reveal(0, 0), 12);
+  assert.equal(game.status, "won");
+});
+
+test("flags toggle and count down", () => {
+  const game = new Game(9, 9, 10, 2);
+  assert.equal(game.flag(3, 3), true);
+  assert.equal(game.view()[3 * 9 + 3], -2);
+  assert.equal(game.minesLeft, 9);
+  assert.equal(game.flag(3, 3), false);
+  assert.equal(game.minesLeft, 10);
+});
+
+test("bad arguments throw", () => {
+  assert.throws(() => new Game(3, 3, 10, 1));
+  assert.throws(() => new Game(9, 9, 10, 1).reveal(9, 0));
+});
+
+test("mines show once the game is lost", () => {
+  const game = new Game(9, 9, 10, 7);
+  assert.ok(!game.view().includes(-4));
+  for (let n = 0; n < 81 && game.status === "playing"; n++) {
+    if (game.view()[n] === -1) {
+      game.reveal(n % 9, Math.floor(n / 9));
+    }
+  }
+  assert.equal(game.status, "lost");
+  const view = Array.from(game.view());
+  assert.equal(view.filter((v) => v === -5).length, 1);
+  assert.equal(view.filter((v) => v === -4).length, 9);
+});
+
+test("wrong flags show once the game is lost", () => {
+  const game = new Game(9, 9, 10, 7);
+  for (let x = 0; x < 9; x++) {
+    game.flag(x, 8);
+  }
+  for (let n = 0; n < 72 && game.status === "playing"; n++) {
+    if (game.view()[n] === -1) {
+      game.reveal(n % 9, Math.floor(n / 9));
+    }
+  }
+  assert.equal(game.status, "lost");
+  const lastRow = Array.from(game.view()).slice(72);
+  assert.ok(lastRow.every((v) => v === -2 || v === -6));
+  assert.ok(lastRow.includes(-6));
+});